@@ -0,0 +1,233 @@
+use std::collections::HashMap;
+
+use crate::{count_matches, PicrossPattern};
+
+impl PicrossPattern {
+    /// Lazily enumerate every concrete `#`/`.` row accepted by this pattern.
+    /// Arrangements are produced in rank order: earlier spans start as far
+    /// left as possible, which is the same order `count_matches` explores them.
+    pub fn arrangements(&self) -> Arrangements {
+        Arrangements::new(self)
+    }
+}
+
+/// Iterator over the arrangements of a `PicrossPattern`.
+///
+/// Nothing is materialized up front: each arrangement is unranked from the
+/// memoized suffix counts, so `nth` and `get` jump straight to an arbitrary
+/// rank. This keeps the unfolded part 2 rows, whose counts run into the
+/// hundreds of thousands, cheap to inspect.
+pub struct Arrangements {
+    match_pattern: String,
+    span_lengths: Vec<usize>,
+    cache: HashMap<PicrossPattern, usize>,
+    next_rank: usize,
+    total: usize,
+}
+
+impl Arrangements {
+    pub fn new(picross_pattern: &PicrossPattern) -> Self {
+        let mut cache: HashMap<PicrossPattern, usize> = HashMap::new();
+        let total = count_arrangements(
+            &picross_pattern.match_pattern,
+            &picross_pattern.span_lengths,
+            &mut cache,
+        );
+
+        Self {
+            match_pattern: picross_pattern.match_pattern.clone(),
+            span_lengths: picross_pattern.span_lengths.clone(),
+            cache,
+            next_rank: 0,
+            total,
+        }
+    }
+
+    /// The total number of arrangements, regardless of iteration progress
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    /// The arrangement with the given rank, or None if `rank >= total()`
+    pub fn get(&mut self, rank: usize) -> Option<String> {
+        if rank >= self.total {
+            return None;
+        }
+        Some(unrank(
+            &self.match_pattern,
+            &self.span_lengths,
+            rank,
+            &mut self.cache,
+        ))
+    }
+}
+
+impl Iterator for Arrangements {
+    type Item = String;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let result = self.get(self.next_rank);
+        if result.is_some() {
+            self.next_rank += 1;
+        }
+        result
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        self.next_rank = self.next_rank.saturating_add(n).min(self.total);
+        self.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.total - self.next_rank;
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for Arrangements {}
+
+/// `count_matches`, extended to the degenerate inputs that show up while
+/// unranking: no spans left, or too little pattern left to hold the spans.
+pub(crate) fn count_arrangements(
+    match_pattern: &str,
+    span_lengths: &[usize],
+    cache: &mut HashMap<PicrossPattern, usize>,
+) -> usize {
+    if span_lengths.is_empty() {
+        return if match_pattern.contains('#') { 0 } else { 1 };
+    }
+
+    let required_length: usize = span_lengths.iter().sum::<usize>() + span_lengths.len() - 1;
+    if match_pattern.len() < required_length {
+        return 0;
+    }
+
+    count_matches(
+        PicrossPattern::new(match_pattern.to_string(), span_lengths.to_vec()),
+        cache,
+    )
+}
+
+/// Can a span of `span_length` start at `start` without contradicting the
+/// pattern? None of its cells may be `.`, and the cell after it (if any)
+/// must be able to act as the `.` separator.
+fn fits(match_pattern: &str, start: usize, span_length: usize) -> bool {
+    let end = start + span_length;
+    !match_pattern[start..end].contains('.') && !match_pattern[end..].starts_with('#')
+}
+
+fn unrank(
+    match_pattern: &str,
+    span_lengths: &[usize],
+    mut rank: usize,
+    cache: &mut HashMap<PicrossPattern, usize>,
+) -> String {
+    let mut result = String::with_capacity(match_pattern.len());
+    let mut offset: usize = 0;
+
+    for (span_idx, span_length) in span_lengths.iter().cloned().enumerate() {
+        let remaining = &match_pattern[offset..];
+        let mut start: usize = 0;
+        loop {
+            if start + span_length > remaining.len() || remaining[..start].contains('#') {
+                panic!("rank {rank} is out of bounds for {match_pattern}");
+            }
+
+            if fits(remaining, start, span_length) {
+                let next = (start + span_length + 1).min(remaining.len());
+                let n_completions =
+                    count_arrangements(&remaining[next..], &span_lengths[span_idx + 1..], cache);
+
+                if rank < n_completions {
+                    result.push_str(&".".repeat(start));
+                    result.push_str(&"#".repeat(span_length));
+                    result.push_str(&".".repeat(next - start - span_length));
+                    offset += next;
+                    break;
+                }
+                rank -= n_completions;
+            }
+            start += 1;
+        }
+    }
+
+    result.push_str(&".".repeat(match_pattern.len() - offset));
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spans_of(arrangement: &str) -> Vec<usize> {
+        arrangement
+            .split('.')
+            .filter(|span| !span.is_empty())
+            .map(|span| span.len())
+            .collect()
+    }
+
+    fn agrees_with(match_pattern: &str, arrangement: &str) -> bool {
+        match_pattern
+            .chars()
+            .zip(arrangement.chars())
+            .all(|(m, a)| m == '?' || m == a)
+    }
+
+    #[test]
+    fn test_arrangements() {
+        let picross_pattern = PicrossPattern::new("?###????????".to_string(), vec![3, 2, 1]);
+        let arrangements: Vec<String> = picross_pattern.arrangements().collect();
+
+        assert_eq!(arrangements.len(), 10);
+        assert_eq!(arrangements[0], ".###.##.#...");
+        assert_eq!(arrangements[9], ".###....##.#");
+        for arrangement in arrangements.iter() {
+            assert_eq!(arrangement.len(), 12);
+            assert_eq!(spans_of(arrangement), vec![3, 2, 1]);
+            assert!(agrees_with("?###????????", arrangement));
+        }
+
+        // '#' sorts before '.', so rank order is lexicographic order
+        let mut sorted = arrangements.clone();
+        sorted.sort();
+        sorted.dedup();
+        assert_eq!(sorted, arrangements);
+    }
+
+    #[test]
+    fn test_arrangements_no_match() {
+        let picross_pattern = PicrossPattern::new("#.#".to_string(), vec![3]);
+        assert_eq!(picross_pattern.arrangements().count(), 0);
+    }
+
+    #[test]
+    fn test_arrangements_nth() {
+        let picross_pattern = PicrossPattern::new(".??..??...?##.".to_string(), vec![1, 1, 3]);
+        let all: Vec<String> = picross_pattern.arrangements().collect();
+        assert_eq!(all.len(), 4);
+
+        let mut arrangements = picross_pattern.arrangements();
+        assert_eq!(arrangements.nth(2), Some(all[2].clone()));
+        assert_eq!(arrangements.len(), 1);
+        assert_eq!(arrangements.next(), Some(all[3].clone()));
+        assert_eq!(arrangements.next(), None);
+    }
+
+    #[test]
+    fn test_arrangements_unfolded() {
+        let reader = shared::input::AocBufReader::from_string("inputs/test.txt");
+        let picross_pattern = crate::parse_input_part_2(reader).pop().unwrap();
+
+        let mut arrangements = picross_pattern.arrangements();
+        assert_eq!(arrangements.total(), 506250);
+
+        for rank in [0, 1, 12345, 506249] {
+            let arrangement = arrangements.get(rank).unwrap();
+            assert_eq!(spans_of(&arrangement), picross_pattern.span_lengths);
+            assert!(agrees_with(&picross_pattern.match_pattern, &arrangement));
+        }
+        assert!(arrangements.get(506250).is_none());
+        assert!(arrangements.get(12344) < arrangements.get(12345));
+    }
+}
@@ -2,7 +2,27 @@ use std::collections::HashMap;
 
 use shared::input::AocBufReader;
 
+mod arrangements;
+mod nonogram;
+
+use nonogram::Nonogram;
+
 fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        match args[0].as_str() {
+            "--nonogram" => {
+                let nonogram = Nonogram::from_reader(AocBufReader::from_string(&args[1]));
+                print!("{}", nonogram.solve());
+            }
+            line_idx => print_arrangements(
+                line_idx.parse::<usize>().unwrap(),
+                args.get(1).map(|rank| rank.parse::<usize>().unwrap()),
+            ),
+        }
+        return;
+    }
+
     let result = part_1(AocBufReader::from_string("inputs/part_1.txt"));
     println!("part 1: {result}");
 
@@ -30,6 +50,30 @@ fn part_2(reader: AocBufReader) -> usize {
         .sum()
 }
 
+/// Every arrangement of row `line_idx` of the input, or with a `rank`, just
+/// that arrangement of the unfolded part 2 row
+fn print_arrangements(line_idx: usize, rank: Option<usize>) {
+    let reader = AocBufReader::from_string("inputs/part_1.txt");
+    match rank {
+        None => {
+            let picross_pattern = parse_input_part_1(reader).swap_remove(line_idx);
+            println!("{}", picross_pattern.match_pattern);
+            for arrangement in picross_pattern.arrangements() {
+                println!("{arrangement}");
+            }
+        }
+        Some(rank) => {
+            let picross_pattern = parse_input_part_2(reader).swap_remove(line_idx);
+            let mut arrangements = picross_pattern.arrangements();
+            let total = arrangements.total();
+            match arrangements.get(rank) {
+                Some(arrangement) => println!("{rank} of {total}: {arrangement}"),
+                None => println!("rank {rank} is out of range, there are {total}"),
+            }
+        }
+    }
+}
+
 fn count_matches(
     picross_pattern: PicrossPattern,
     cache: &mut HashMap<PicrossPattern, usize>,
@@ -81,10 +125,7 @@ fn count_matches(
             {
                 let remainder = PicrossPattern::new(
                     picross_pattern.match_pattern[split_at + 1..].to_string(),
-                    picross_pattern.span_lengths[1..]
-                        .iter()
-                        .cloned()
-                        .collect::<Vec<usize>>(),
+                    picross_pattern.span_lengths[1..].to_vec(),
                 );
                 result += count_matches(remainder, cache);
             }
@@ -133,10 +174,10 @@ impl PicrossPattern {
         let n_spans = span_lengths.len();
 
         Self {
-            match_pattern: match_pattern,
-            match_pattern_len: match_pattern_len,
-            span_lengths: span_lengths,
-            n_spans: n_spans,
+            match_pattern,
+            match_pattern_len,
+            span_lengths,
+            n_spans,
         }
     }
 }
@@ -0,0 +1,296 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use shared::input::AocBufReader;
+
+use crate::arrangements::count_arrangements;
+use crate::PicrossPattern;
+
+/// A 2D nonogram: every row and every column is a day 12 picross clue, and
+/// a solution has to satisfy all of them at once.
+pub struct Nonogram {
+    row_clues: Vec<Vec<usize>>,
+    col_clues: Vec<Vec<usize>>,
+}
+
+#[derive(Debug)]
+pub enum NonogramSolution {
+    Unsolvable,
+    Unique(NonogramGrid),
+    /// Two distinct solutions; there may be more
+    Multiple(NonogramGrid, NonogramGrid),
+}
+
+/// A (possibly partially) solved nonogram, using the same `#`, `.` and `?`
+/// characters as the day 12 input
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NonogramGrid {
+    cells: Vec<Vec<char>>,
+}
+
+impl NonogramGrid {
+    fn unknown(n_rows: usize, n_cols: usize) -> Self {
+        Self {
+            cells: vec![vec!['?'; n_cols]; n_rows],
+        }
+    }
+
+    fn row(&self, row: usize) -> String {
+        self.cells[row].iter().collect()
+    }
+
+    fn col(&self, col: usize) -> String {
+        self.cells.iter().map(|row| row[col]).collect()
+    }
+
+    fn first_unknown(&self) -> Option<(usize, usize)> {
+        self.cells.iter().enumerate().find_map(|(row_idx, row)| {
+            row.iter()
+                .position(|c| *c == '?')
+                .map(|col_idx| (row_idx, col_idx))
+        })
+    }
+}
+
+impl fmt::Display for NonogramGrid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for row in self.cells.iter() {
+            writeln!(f, "{}", row.iter().collect::<String>())?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for NonogramSolution {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Unsolvable => writeln!(f, "unsolvable"),
+            Self::Unique(grid) => write!(f, "unique solution:\n{grid}"),
+            Self::Multiple(first, second) => {
+                write!(f, "multiple solutions, including:\n{first}\n{second}")
+            }
+        }
+    }
+}
+
+impl Nonogram {
+    /// Clues of `[0]` and `[]` both describe an empty line
+    pub fn new(row_clues: Vec<Vec<usize>>, col_clues: Vec<Vec<usize>>) -> Self {
+        let normalize = |clues: Vec<Vec<usize>>| -> Vec<Vec<usize>> {
+            clues
+                .into_iter()
+                .map(|clue| clue.into_iter().filter(|l| *l > 0).collect())
+                .collect()
+        };
+
+        Self {
+            row_clues: normalize(row_clues),
+            col_clues: normalize(col_clues),
+        }
+    }
+
+    /// One clue per line, e.g. `3,1`, for the rows, then a blank line, then
+    /// the columns. `0` is an empty line.
+    pub fn from_reader(reader: AocBufReader) -> Self {
+        let lines: Vec<String> = reader.collect();
+        let mut sections = lines.split(|line| line.trim().is_empty());
+        let mut parse_clues = || -> Vec<Vec<usize>> {
+            sections
+                .next()
+                .expect("expected row clues, a blank line, then column clues")
+                .iter()
+                .map(|line| {
+                    line.split(',')
+                        .map(|l| l.trim().parse::<usize>().unwrap())
+                        .collect()
+                })
+                .collect()
+        };
+        let row_clues = parse_clues();
+        let col_clues = parse_clues();
+        Self::new(row_clues, col_clues)
+    }
+
+    /// Line-solve until nothing changes, then backtrack on the first unknown
+    /// cell. The search stops as soon as a second solution turns up.
+    pub fn solve(&self) -> NonogramSolution {
+        let mut cache: HashMap<PicrossPattern, usize> = HashMap::new();
+        let mut solutions: Vec<NonogramGrid> = Vec::new();
+        let grid = NonogramGrid::unknown(self.row_clues.len(), self.col_clues.len());
+        self.search(grid, &mut solutions, &mut cache);
+
+        let mut solutions = solutions.into_iter();
+        match (solutions.next(), solutions.next()) {
+            (None, _) => NonogramSolution::Unsolvable,
+            (Some(solution), None) => NonogramSolution::Unique(solution),
+            (Some(first), Some(second)) => NonogramSolution::Multiple(first, second),
+        }
+    }
+
+    fn search(
+        &self,
+        mut grid: NonogramGrid,
+        solutions: &mut Vec<NonogramGrid>,
+        cache: &mut HashMap<PicrossPattern, usize>,
+    ) {
+        if !self.propagate(&mut grid, cache) {
+            return;
+        }
+
+        match grid.first_unknown() {
+            None => solutions.push(grid),
+            Some((row, col)) => {
+                for guess in ['#', '.'] {
+                    let mut guessed = grid.clone();
+                    guessed.cells[row][col] = guess;
+                    self.search(guessed, solutions, cache);
+                    if solutions.len() >= 2 {
+                        return;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Apply `solve_line` to every row and column until a fixed point.
+    /// Returns false if some line has no arrangement left.
+    fn propagate(
+        &self,
+        grid: &mut NonogramGrid,
+        cache: &mut HashMap<PicrossPattern, usize>,
+    ) -> bool {
+        let mut changed = true;
+        while changed {
+            changed = false;
+
+            for (row_idx, clue) in self.row_clues.iter().enumerate() {
+                let line = grid.row(row_idx);
+                let Some(solved) = solve_line(&line, clue, cache) else {
+                    return false;
+                };
+                if solved != line {
+                    changed = true;
+                    grid.cells[row_idx] = solved.chars().collect();
+                }
+            }
+
+            for (col_idx, clue) in self.col_clues.iter().enumerate() {
+                let line = grid.col(col_idx);
+                let Some(solved) = solve_line(&line, clue, cache) else {
+                    return false;
+                };
+                if solved != line {
+                    changed = true;
+                    for (row_idx, c) in solved.chars().enumerate() {
+                        grid.cells[row_idx][col_idx] = c;
+                    }
+                }
+            }
+        }
+        true
+    }
+}
+
+/// Fill in every `?` in `line` that takes the same value in all arrangements
+/// of `clue`. Arrangements with a cell set to `#` and those with it set to `.`
+/// partition the total, so one count per unknown cell is enough.
+fn solve_line(
+    line: &str,
+    clue: &[usize],
+    cache: &mut HashMap<PicrossPattern, usize>,
+) -> Option<String> {
+    let total = count_arrangements(line, clue, cache);
+    if total == 0 {
+        return None;
+    }
+
+    let mut result: Vec<char> = line.chars().collect();
+    for idx in 0..result.len() {
+        if result[idx] != '?' {
+            continue;
+        }
+
+        let mut filled: Vec<char> = line.chars().collect();
+        filled[idx] = '#';
+        let n_filled = count_arrangements(&filled.into_iter().collect::<String>(), clue, cache);
+        if n_filled == 0 {
+            result[idx] = '.';
+        } else if n_filled == total {
+            result[idx] = '#';
+        }
+    }
+
+    Some(result.into_iter().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_solve_line() {
+        let mut cache: HashMap<PicrossPattern, usize> = HashMap::new();
+
+        assert_eq!(
+            solve_line("??????????", &[8], &mut cache),
+            Some("??######??".to_string())
+        );
+        assert_eq!(solve_line("?#???", &[], &mut cache), None);
+        assert_eq!(
+            solve_line("?#???", &[1, 1], &mut cache),
+            Some(".#.??".to_string())
+        );
+    }
+
+    #[test]
+    fn test_solve_unique() {
+        let nonogram = Nonogram::new(
+            vec![vec![3], vec![1, 1], vec![5], vec![1, 1], vec![1, 1]],
+            vec![vec![4], vec![1, 1], vec![1, 1], vec![1, 1], vec![4]],
+        );
+
+        match nonogram.solve() {
+            NonogramSolution::Unique(grid) => {
+                assert_eq!(grid.to_string(), ".###.\n#...#\n#####\n#...#\n#...#\n")
+            }
+            other => panic!("expected a unique solution, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_from_reader() {
+        let nonogram = Nonogram::from_reader(AocBufReader::from_string("inputs/nonogram.txt"));
+        assert_eq!(
+            nonogram.row_clues,
+            vec![vec![3], vec![1, 1], vec![5], vec![1, 1], vec![]]
+        );
+        assert_eq!(
+            nonogram.col_clues,
+            vec![vec![3], vec![1, 1], vec![1, 1], vec![1, 1], vec![3]]
+        );
+        assert_eq!(
+            nonogram.solve().to_string(),
+            "unique solution:\n.###.\n#...#\n#####\n#...#\n.....\n"
+        );
+    }
+
+    #[test]
+    fn test_solve_multiple() {
+        // the two diagonals of a 2x2 grid
+        let nonogram = Nonogram::new(vec![vec![1], vec![1]], vec![vec![1], vec![1]]);
+
+        match nonogram.solve() {
+            NonogramSolution::Multiple(first, second) => {
+                assert_eq!(first.to_string(), "#.\n.#\n");
+                assert_eq!(second.to_string(), ".#\n#.\n");
+            }
+            other => panic!("expected multiple solutions, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_solve_unsolvable() {
+        let nonogram = Nonogram::new(vec![vec![2], vec![0]], vec![vec![0], vec![1]]);
+        assert!(matches!(nonogram.solve(), NonogramSolution::Unsolvable));
+    }
+}
@@ -1,8 +1,20 @@
+use std::collections::HashMap;
 use std::mem::take;
 
+use shared::coords::UCoord;
 use shared::input::AocBufReader;
 
+mod symmetry;
+
 fn main() {
+    if std::env::args().nth(1).as_deref() == Some("--symmetry") {
+        let maps = parse_input(AocBufReader::from_string("inputs/part_1.txt"));
+        for (idx, map) in maps.iter().enumerate() {
+            println!("pattern {idx}: {}", map.symmetry());
+        }
+        return;
+    }
+
     let result = part_1(AocBufReader::from_string("inputs/part_1.txt"));
     println!("part 1: {result}");
 
@@ -12,159 +24,449 @@ fn main() {
 
 fn part_1(reader: AocBufReader) -> usize {
     let maps = parse_input(reader);
-    maps.iter()
-        .map(|m| {
-            let lr_reflection = m.left_right_reflection();
-            let ud_reflection = m.up_down_reflection();
-            match (lr_reflection, ud_reflection) {
-                (Some(lr), None) => lr,
-                (None, Some(ud)) => 100 * ud,
-                _ => panic!("too many or too few mirrors!"),
-            }
-        })
-        .sum()
+    maps.iter().map(|m| summarize(m, 0)).sum()
 }
 
 fn part_2(reader: AocBufReader) -> usize {
     let maps = parse_input(reader);
-    maps.iter()
-        .map(|m| {
-            let lr_smudge = m.left_right_smudge();
-            let ud_smudge = m.up_down_smudge();
-            match (lr_smudge, ud_smudge) {
-                (Some(lr), None) => lr,
-                (None, Some(ud)) => 100 * ud,
-                _ => panic!("too many or too few mirrors!"),
-            }
-        })
-        .sum()
+    maps.iter().map(|m| summarize(m, 1)).sum()
 }
 
-struct Map {
-    n_rows: usize,
-    n_cols: usize,
-    rows: Vec<String>,
-    cols: Vec<String>,
-    row_chars: Vec<Vec<char>>,
-    col_chars: Vec<Vec<char>>,
+fn summarize(map: &Map, n_smudges: usize) -> usize {
+    match map.reflections(n_smudges)[..] {
+        [Reflection {
+            axis: Axis::LeftRight(lr),
+            ..
+        }] => lr,
+        [Reflection {
+            axis: Axis::UpDown(ud),
+            ..
+        }] => 100 * ud,
+        _ => panic!("too many or too few mirrors!"),
+    }
 }
 
-impl Map {
-    fn left_right_plane_w_n_diffs(&self, n_diffs: usize) -> Option<usize> {
-        for col_idx in 1..self.n_cols {
-            let left = &self.cols[..col_idx];
-            let left_len = col_idx;
-
-            let right = &self.cols[col_idx..];
-            let right_len = self.n_cols - col_idx;
-
-            let short_side_len = left_len.min(right_len);
-            let n_differences: usize = left[(left_len - short_side_len)..]
-                .iter()
-                .rev()
-                .zip(right[..short_side_len].iter())
-                .map(|(left_s, right_s)| count_differences(left_s, right_s))
-                .sum();
-
-            if n_differences == n_diffs {
-                return Some(col_idx);
-            }
+/// A mirror plane. `LeftRight(n)` sits between columns `n - 1` and `n`, and
+/// `UpDown(n)` between rows `n - 1` and `n`, so `n` is what the puzzle scores.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Axis {
+    LeftRight(usize),
+    UpDown(usize),
+}
+
+/// A mirror plane together with the pairs of mirrored cells (the smudges)
+/// that disagree across it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reflection {
+    pub axis: Axis,
+    pub smudges: Vec<(UCoord, UCoord)>,
+}
+
+/// A row or column as a bitset (bit `i` set means cell `i` is `#`), packed
+/// 64 cells to a word, so comparing two lines is a XOR and popcount per word
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Line {
+    words: Vec<u64>,
+}
+
+impl Line {
+    fn new(len: usize) -> Self {
+        Self {
+            words: vec![0; len.div_ceil(64)],
         }
-        None
     }
 
-    fn up_down_plane_w_n_diffs(&self, n_diffs: usize) -> Option<usize> {
-        for row_idx in 1..self.n_rows {
-            let up = &self.rows[..row_idx];
-            let up_len = row_idx;
+    fn set(&mut self, idx: usize) {
+        self.words[idx / 64] |= 1 << (idx % 64);
+    }
 
-            let down = &self.rows[row_idx..];
-            let down_len = self.n_rows - row_idx;
+    fn contains(&self, idx: usize) -> bool {
+        self.words[idx / 64] & (1 << (idx % 64)) != 0
+    }
 
-            let short_side_len = up_len.min(down_len);
-            let n_differences: usize = up[(up_len - short_side_len)..]
-                .iter()
-                .rev()
-                .zip(down[..short_side_len].iter())
-                .map(|(up_s, down_s)| count_differences(up_s, down_s))
-                .sum();
+    fn n_differences(&self, other: &Line) -> usize {
+        self.words
+            .iter()
+            .zip(other.words.iter())
+            .map(|(word, other_word)| (word ^ other_word).count_ones() as usize)
+            .sum()
+    }
 
-            if n_differences == n_diffs {
-                return Some(row_idx);
+    /// The cells that differ between the two lines
+    fn differences(&self, other: &Line) -> Vec<usize> {
+        let mut result: Vec<usize> = Vec::new();
+        for (word_idx, (word, other_word)) in self.words.iter().zip(other.words.iter()).enumerate()
+        {
+            let mut differences = word ^ other_word;
+            while differences != 0 {
+                result.push(word_idx * 64 + differences.trailing_zeros() as usize);
+                differences &= differences - 1;
             }
         }
-        None
+        result
     }
 
-    fn left_right_reflection(&self) -> Option<usize> {
-        self.left_right_plane_w_n_diffs(0)
+    /// The first `len` cells in reverse order
+    fn reversed(&self, len: usize) -> Self {
+        let mut result = Self::new(len);
+        for idx in (0..len).filter(|idx| self.contains(*idx)) {
+            result.set(len - 1 - idx);
+        }
+        result
     }
+}
+
+struct Map {
+    rows: Vec<Line>,
+    cols: Vec<Line>,
+}
+
+impl Map {
+    /// Panics on an empty or ragged pattern
+    fn from_lines(lines: &[String]) -> Self {
+        assert!(
+            !lines.is_empty() && !lines[0].is_empty(),
+            "a pattern needs at least one row and one column"
+        );
+        let n_rows = lines.len();
+        let n_cols = lines[0].len();
+        assert!(
+            lines.iter().all(|line| line.len() == n_cols),
+            "every row of a pattern must be {n_cols} wide"
+        );
+
+        let mut rows: Vec<Line> = vec![Line::new(n_cols); n_rows];
+        let mut cols: Vec<Line> = vec![Line::new(n_rows); n_cols];
+        for (row_idx, line) in lines.iter().enumerate() {
+            for (col_idx, c) in line.chars().enumerate() {
+                if c == '#' {
+                    rows[row_idx].set(col_idx);
+                    cols[col_idx].set(row_idx);
+                }
+            }
+        }
 
-    fn up_down_reflection(&self) -> Option<usize> {
-        self.up_down_plane_w_n_diffs(0)
+        Self { rows, cols }
     }
 
-    fn left_right_smudge(&self) -> Option<usize> {
-        self.left_right_plane_w_n_diffs(1)
+    /// Every mirror plane, vertical planes first, whose mirrored cells differ
+    /// in exactly `n_smudges` places
+    pub fn reflections(&self, n_smudges: usize) -> Vec<Reflection> {
+        let left_right = mirror_planes(&self.cols, n_smudges)
+            .into_iter()
+            .map(|col_idx| Reflection {
+                axis: Axis::LeftRight(col_idx),
+                smudges: mirror_differences(&self.cols, col_idx)
+                    .into_iter()
+                    .map(|(col, mirror_col, row)| {
+                        (UCoord::new(row, col), UCoord::new(row, mirror_col))
+                    })
+                    .collect(),
+            });
+        let up_down = mirror_planes(&self.rows, n_smudges)
+            .into_iter()
+            .map(|row_idx| Reflection {
+                axis: Axis::UpDown(row_idx),
+                smudges: mirror_differences(&self.rows, row_idx)
+                    .into_iter()
+                    .map(|(row, mirror_row, col)| {
+                        (UCoord::new(row, col), UCoord::new(mirror_row, col))
+                    })
+                    .collect(),
+            });
+
+        left_right.chain(up_down).collect()
     }
+}
 
-    fn up_down_smudge(&self) -> Option<usize> {
-        self.up_down_plane_w_n_diffs(1)
+/// Every plane between `lines[plane - 1]` and `lines[plane]` whose mirrored
+/// pairs differ in exactly `n_diffs` bits. Lines are numbered so equal lines
+/// share an id, and then for every plane the run of equal pairs out from the
+/// plane (Manacher's algorithm) and in from the edge of the pattern (the
+/// Z-function) are known in O(n) for `n` lines. If the two runs meet, at
+/// most one pair differs and it is the only pair that needs a XOR, so with
+/// the puzzle's budgets of 0 and 1 the whole scan is O(n). A plane where two
+/// or more pairs differ costs O(1) too unless `n_diffs` is at least 2; only
+/// then are the pairs between them compared, until the budget runs out.
+fn mirror_planes(lines: &[Line], n_diffs: usize) -> Vec<usize> {
+    let n = lines.len();
+    let ids = line_ids(lines);
+    let from_plane = even_palindrome_radii(&ids);
+    let reversed: Vec<usize> = ids.iter().rev().cloned().collect();
+    // lines read forwards then backwards, and the other way round
+    let forwards_z = z_function(&[&ids[..], &[usize::MAX], &reversed[..]].concat());
+    let backwards_z = z_function(&[&reversed[..], &[usize::MAX], &ids[..]].concat());
+    let pair = |plane: usize, offset: usize| (&lines[plane - 1 - offset], &lines[plane + offset]);
+
+    (1..n)
+        .filter(|plane| {
+            let plane = *plane;
+            let n_pairs = plane.min(n - plane);
+            // equal pairs in from the first or last line, whichever is mirrored
+            let from_edge = if 2 * plane <= n {
+                forwards_z[2 * n + 1 - 2 * plane]
+            } else {
+                backwards_z[2 * plane + 1]
+            };
+
+            let inner = from_plane[plane];
+            if inner >= n_pairs {
+                return n_diffs == 0;
+            }
+            let outer = n_pairs - 1 - from_edge.min(n_pairs - 1);
+            let (before, after) = pair(plane, inner);
+            let mut n_differences = before.n_differences(after);
+            if outer == inner {
+                return n_differences == n_diffs;
+            }
+
+            let (before, after) = pair(plane, outer);
+            n_differences += before.n_differences(after);
+            for offset in inner + 1..outer {
+                if n_differences > n_diffs {
+                    return false;
+                }
+                let (before, after) = pair(plane, offset);
+                n_differences += before.n_differences(after);
+            }
+            n_differences == n_diffs
+        })
+        .collect()
+}
+
+/// The same id for equal lines, so they can be compared in O(1)
+fn line_ids(lines: &[Line]) -> Vec<usize> {
+    let mut ids: HashMap<&Line, usize> = HashMap::new();
+    lines
+        .iter()
+        .map(|line| {
+            let next_id = ids.len();
+            *ids.entry(line).or_insert(next_id)
+        })
+        .collect()
+}
+
+/// `result[i]`: how many pairs `(i - 1 - k, i + k)` are equal, counting out
+/// from the gap before `i` until the first unequal pair (Manacher's algorithm
+/// for even palindromes)
+fn even_palindrome_radii(ids: &[usize]) -> Vec<usize> {
+    let n = ids.len();
+    let mut result: Vec<usize> = vec![0; n];
+    // the palindrome reaching furthest right so far covers `left..right`
+    let (mut left, mut right) = (0, 0);
+    for i in 1..n {
+        let mut radius = if i < right {
+            result[left + right - i].min(right - i)
+        } else {
+            0
+        };
+        while i + radius < n && radius < i && ids[i + radius] == ids[i - 1 - radius] {
+            radius += 1;
+        }
+        result[i] = radius;
+        if i + radius > right {
+            (left, right) = (i - radius, i + radius);
+        }
+    }
+    result
+}
+
+/// `result[i]`: the length of the longest common prefix of `s` and `s[i..]`
+fn z_function(s: &[usize]) -> Vec<usize> {
+    let n = s.len();
+    let mut result: Vec<usize> = vec![0; n];
+    result[0] = n;
+    let (mut left, mut right) = (0, 0);
+    for i in 1..n {
+        let mut len = if i < right {
+            result[i - left].min(right - i)
+        } else {
+            0
+        };
+        while i + len < n && s[len] == s[i + len] {
+            len += 1;
+        }
+        result[i] = len;
+        if i + len > right {
+            (left, right) = (i, i + len);
+        }
     }
+    result
 }
 
-fn count_differences(s1: &str, s2: &str) -> usize {
-    s1.chars()
-        .zip(s2.chars())
-        .filter(|(c1, c2)| c1 != c2)
-        .count()
+/// `(line, mirrored line, cell)` for every cell that differs across `plane`
+fn mirror_differences(lines: &[Line], plane: usize) -> Vec<(usize, usize, usize)> {
+    let mut result: Vec<(usize, usize, usize)> = Vec::new();
+    for (offset, (before, after)) in lines[..plane]
+        .iter()
+        .rev()
+        .zip(lines[plane..].iter())
+        .enumerate()
+    {
+        for cell in before.differences(after) {
+            result.push((plane - 1 - offset, plane + offset, cell));
+        }
+    }
+    result
 }
 
-fn parse_input(mut reader: AocBufReader) -> Vec<Map> {
+fn parse_input(reader: AocBufReader) -> Vec<Map> {
     let mut result: Vec<Map> = Vec::new();
 
-    let mut rows: Vec<String> = Vec::new();
-    let mut row_chars: Vec<Vec<char>> = Vec::new();
-    let mut col_chars: Vec<Vec<char>> = Vec::new();
-    while let Some(line) = reader.next() {
+    let mut lines: Vec<String> = Vec::new();
+    for line in reader {
         if line.is_empty() {
-            let cols: Vec<String> = col_chars
-                .iter()
-                .map(|col| col.into_iter().collect::<String>())
-                .collect();
-            result.push(Map {
-                n_rows: rows.len(),
-                n_cols: cols.len(),
-                rows: take(&mut rows),
-                cols: cols,
-                row_chars: take(&mut row_chars),
-                col_chars: take(&mut col_chars),
-            });
+            result.push(Map::from_lines(&take(&mut lines)));
         } else {
-            row_chars.push(line.chars().collect::<Vec<char>>());
-            if col_chars.is_empty() {
-                let n_cols = line.len();
-                col_chars = vec![Vec::new(); n_cols];
-            }
-            for (col_idx, c) in line.chars().enumerate() {
-                col_chars[col_idx].push(c);
+            lines.push(line);
+        }
+    }
+    if !lines.is_empty() {
+        result.push(Map::from_lines(&lines));
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_part_1() {
+        assert_eq!(part_1(AocBufReader::from_string("inputs/test.txt")), 405);
+    }
+
+    #[test]
+    fn test_part_2() {
+        assert_eq!(part_2(AocBufReader::from_string("inputs/test.txt")), 400);
+    }
+
+    #[test]
+    fn test_reflections() {
+        let maps = parse_input(AocBufReader::from_string("inputs/test.txt"));
+
+        assert_eq!(
+            maps[0].reflections(0),
+            vec![Reflection {
+                axis: Axis::LeftRight(5),
+                smudges: vec![]
+            }]
+        );
+        assert_eq!(
+            maps[0].reflections(1),
+            vec![Reflection {
+                axis: Axis::UpDown(3),
+                smudges: vec![(UCoord::new(0, 0), UCoord::new(5, 0))]
+            }]
+        );
+        assert_eq!(
+            maps[1].reflections(1),
+            vec![Reflection {
+                axis: Axis::UpDown(1),
+                smudges: vec![(UCoord::new(0, 4), UCoord::new(1, 4))]
+            }]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "a pattern needs at least one row and one column")]
+    fn test_empty_pattern() {
+        Map::from_lines(&[]);
+    }
+
+    #[test]
+    fn test_large_pattern() {
+        // 100 rows of 70 cells, mirrored between rows 60 and 61 and between
+        // columns 35 and 36, with one smudge at row 0 column 69
+        let lines: Vec<String> = (0..100)
+            .map(|row: usize| {
+                let row = if row < 60 { row } else { 119 - row };
+                let half: String = (0..35)
+                    .map(|col| {
+                        if (row * 7 + col * 3) % 5 == 0 {
+                            '#'
+                        } else {
+                            '.'
+                        }
+                    })
+                    .collect();
+                half.clone() + &half.chars().rev().collect::<String>()
+            })
+            .collect();
+        let mut smudged = lines.clone();
+        smudged[0].replace_range(69..70, if lines[0].ends_with('#') { "." } else { "#" });
+
+        let map = Map::from_lines(&lines);
+        let axes = |reflections: Vec<Reflection>| -> Vec<Axis> {
+            reflections.into_iter().map(|r| r.axis).collect()
+        };
+        assert_eq!(
+            axes(map.reflections(0)),
+            vec![Axis::LeftRight(35), Axis::UpDown(60)]
+        );
+        let map = Map::from_lines(&smudged);
+        assert_eq!(axes(map.reflections(0)), vec![Axis::UpDown(60)]);
+        assert_eq!(
+            map.reflections(1),
+            vec![Reflection {
+                axis: Axis::LeftRight(35),
+                smudges: vec![(UCoord::new(0, 0), UCoord::new(0, 69))]
+            }]
+        );
+    }
+
+    /// Every plane with exactly `n_diffs` differing bits, one pair at a time
+    fn mirror_planes_brute_force(lines: &[Line], n_diffs: usize) -> Vec<usize> {
+        (1..lines.len())
+            .filter(|plane| {
+                lines[..*plane]
+                    .iter()
+                    .rev()
+                    .zip(lines[*plane..].iter())
+                    .map(|(before, after)| before.n_differences(after))
+                    .sum::<usize>()
+                    == n_diffs
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_mirror_planes_matches_brute_force() {
+        // every sequence of up to 9 lines drawn from 3 patterns, 2 bits apart
+        let patterns: Vec<Line> = ["..", "#.", "##"]
+            .iter()
+            .map(|p| Map::from_lines(&[p.to_string()]).rows.remove(0))
+            .collect();
+        for n in 1..=9 {
+            for code in 0..3usize.pow(n) {
+                let lines: Vec<Line> = (0..n)
+                    .map(|idx| patterns[code / 3usize.pow(idx) % 3].clone())
+                    .collect();
+                for n_diffs in 0..5 {
+                    assert_eq!(
+                        mirror_planes(&lines, n_diffs),
+                        mirror_planes_brute_force(&lines, n_diffs),
+                        "{lines:?} with {n_diffs} differences"
+                    );
+                }
             }
-            rows.push(line);
         }
     }
 
-    let cols: Vec<String> = col_chars
-        .iter()
-        .map(|col| col.into_iter().collect::<String>())
-        .collect();
-    result.push(Map {
-        n_rows: rows.len(),
-        n_cols: cols.len(),
-        rows: rows,
-        cols: cols,
-        row_chars: row_chars,
-        col_chars: col_chars,
-    });
+    #[test]
+    #[should_panic(expected = "every row of a pattern must be 2 wide")]
+    fn test_ragged_pattern() {
+        Map::from_lines(&["#.".to_string(), "#..".to_string()]);
+    }
 
-    result
+    #[test]
+    fn test_reflections_many_smudges() {
+        let maps = parse_input(AocBufReader::from_string("inputs/test.txt"));
+        for k in 0..4 {
+            for reflection in maps[1].reflections(k) {
+                assert_eq!(reflection.smudges.len(), k);
+            }
+        }
+    }
 }
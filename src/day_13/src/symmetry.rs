@@ -0,0 +1,155 @@
+use std::fmt;
+
+use crate::{Axis, Line, Map};
+
+/// Everything a pattern is invariant under, not just the puzzle's mirrors.
+/// The diagonal and quarter-turn checks only apply to square patterns and
+/// are always false otherwise.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symmetry {
+    /// Exact mirror planes, in the same form as `Map::reflections(0)`
+    pub mirrors: Vec<Axis>,
+    pub rotation_90: bool,
+    pub rotation_180: bool,
+    /// Invariant under transposition, i.e. cell (r, c) == cell (c, r)
+    pub main_diagonal: bool,
+    /// Invariant under reflection through the top-right to bottom-left diagonal
+    pub anti_diagonal: bool,
+    /// Smallest `p` such that every row equals the row `p` below it
+    pub row_period: Option<usize>,
+    /// Smallest `p` such that every column equals the column `p` to its right
+    pub col_period: Option<usize>,
+}
+
+impl fmt::Display for Symmetry {
+    /// e.g. `mirrors [UpDown(4)], rotation 180, row period 2`, or `none`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut features: Vec<String> = Vec::new();
+        if !self.mirrors.is_empty() {
+            features.push(format!("mirrors {:?}", self.mirrors));
+        }
+        for (has_feature, name) in [
+            (self.rotation_90, "rotation 90"),
+            (self.rotation_180, "rotation 180"),
+            (self.main_diagonal, "main diagonal"),
+            (self.anti_diagonal, "anti-diagonal"),
+        ] {
+            if has_feature {
+                features.push(name.to_string());
+            }
+        }
+        if let Some(p) = self.row_period {
+            features.push(format!("row period {p}"));
+        }
+        if let Some(p) = self.col_period {
+            features.push(format!("column period {p}"));
+        }
+        match features.is_empty() {
+            true => write!(f, "none"),
+            false => write!(f, "{}", features.join(", ")),
+        }
+    }
+}
+
+impl Map {
+    pub fn symmetry(&self) -> Symmetry {
+        let n_rows = self.rows.len();
+        let n_cols = self.cols.len();
+        let is_square = n_rows == n_cols;
+
+        let rotation_180 = (0..n_rows)
+            .all(|row_idx| self.rows[row_idx].reversed(n_cols) == self.rows[n_rows - 1 - row_idx]);
+        let rotation_90 =
+            is_square && (0..n_rows).all(|idx| self.rows[idx] == self.cols[idx].reversed(n_rows));
+        let main_diagonal = is_square && self.rows == self.cols;
+        let anti_diagonal = is_square
+            && (0..n_cols)
+                .all(|idx| self.cols[idx] == self.rows[n_rows - 1 - idx].reversed(n_cols));
+
+        Symmetry {
+            mirrors: self
+                .reflections(0)
+                .into_iter()
+                .map(|reflection| reflection.axis)
+                .collect(),
+            rotation_90,
+            rotation_180,
+            main_diagonal,
+            anti_diagonal,
+            row_period: period(&self.rows),
+            col_period: period(&self.cols),
+        }
+    }
+}
+
+/// The smallest shift that maps `lines` onto itself, if shorter than `lines`
+fn period(lines: &[Line]) -> Option<usize> {
+    (1..lines.len()).find(|p| (0..lines.len() - p).all(|idx| lines[idx] == lines[idx + p]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map(lines: &[&str]) -> Map {
+        Map::from_lines(&lines.iter().map(|l| l.to_string()).collect::<Vec<String>>())
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(map(&["#.", ".."]).symmetry().to_string(), "main diagonal");
+        assert_eq!(
+            map(&["#.##", "#...", "...#", "##.#"])
+                .symmetry()
+                .to_string(),
+            "rotation 90, rotation 180"
+        );
+        assert_eq!(
+            map(&["#.#.", "#.#."]).symmetry().to_string(),
+            "mirrors [UpDown(1)], row period 1, column period 2"
+        );
+    }
+
+    #[test]
+    fn test_symmetry_pinwheel() {
+        let symmetry = map(&["##.", "...", ".##"]).symmetry();
+        assert!(!symmetry.rotation_90);
+        assert!(symmetry.rotation_180);
+
+        let symmetry = map(&["#.##", "#...", "...#", "##.#"]).symmetry();
+        assert!(symmetry.rotation_90);
+        assert!(symmetry.rotation_180);
+        assert!(!symmetry.main_diagonal);
+        assert!(!symmetry.anti_diagonal);
+        assert!(symmetry.mirrors.is_empty());
+    }
+
+    #[test]
+    fn test_symmetry_diagonals() {
+        let symmetry = map(&["##.", "#..", "..."]).symmetry();
+        assert!(symmetry.main_diagonal);
+        assert!(!symmetry.anti_diagonal);
+
+        let symmetry = map(&[".##", "..#", "..."]).symmetry();
+        assert!(!symmetry.main_diagonal);
+        assert!(symmetry.anti_diagonal);
+    }
+
+    #[test]
+    fn test_symmetry_period() {
+        let symmetry = map(&["#..#..#", ".#..#..", "#..#..#", ".#..#.."]).symmetry();
+        assert_eq!(symmetry.row_period, Some(2));
+        assert_eq!(symmetry.col_period, Some(3));
+        assert!(!symmetry.rotation_90);
+        assert!(!symmetry.main_diagonal);
+    }
+
+    #[test]
+    fn test_symmetry_puzzle_mirrors() {
+        let maps = crate::parse_input(shared::input::AocBufReader::from_string("inputs/test.txt"));
+        let symmetry = maps[0].symmetry();
+        assert_eq!(symmetry.mirrors, vec![Axis::LeftRight(5)]);
+        assert_eq!(symmetry.row_period, None);
+        assert!(!symmetry.rotation_180);
+    }
+}
@@ -1,5 +1,7 @@
-#![feature(linked_list_remove)]
-use std::collections::LinkedList;
+#![feature(hasher_prefixfree_extras)]
+use std::borrow::Borrow;
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::ops::Index;
 
 use once_cell::sync::Lazy;
 use regex::Regex;
@@ -29,11 +31,15 @@ fn part_1(mut reader: AocBufReader) -> usize {
 fn part_2(mut reader: AocBufReader) -> usize {
     let line = reader.next().unwrap();
 
-    let mut hash_map: AoCHashMap = AoCHashMap::new();
+    let mut hash_map: AoCHashMap<String, usize> = AoCHashMap::new();
     for x in line.split(",") {
         match HashMapInstruction::from_str(x) {
-            HashMapInstruction::Insert(label, focal_length) => hash_map.insert(label, focal_length),
-            HashMapInstruction::Remove(label) => hash_map.remove(label),
+            HashMapInstruction::Insert(label, focal_length) => {
+                hash_map.insert(label, focal_length);
+            }
+            HashMapInstruction::Remove(label) => {
+                hash_map.remove(&label);
+            }
         }
     }
 
@@ -41,33 +47,125 @@ fn part_2(mut reader: AocBufReader) -> usize {
 }
 
 fn hash(s: String) -> u8 {
-    s.into_bytes().into_iter().fold(0, |acc, x| {
-        acc.wrapping_add(x).wrapping_mul(HASH_MULTIPLIER)
-    })
+    BuildAocHasher.hash_one(s) as u8
 }
 
-struct AoCHashMap {
-    boxes: [AocBox; 256],
+/// The puzzle's HASH algorithm as a `Hasher`; `finish` is always below 256
+#[derive(Default)]
+pub struct AocHasher {
+    state: u8,
 }
 
-impl AoCHashMap {
-    fn new() -> Self {
-        let boxes: [AocBox; 256] = std::array::from_fn(|_| AocBox::new());
-        AoCHashMap { boxes: boxes }
+impl Hasher for AocHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        self.state = bytes.iter().fold(self.state, |acc, x| {
+            acc.wrapping_add(*x).wrapping_mul(HASH_MULTIPLIER)
+        });
+    }
+
+    /// The default appends a 0xff terminator to every `str`, which would
+    /// change the HASH of a label, so strings hash as their bytes alone
+    fn write_str(&mut self, s: &str) {
+        self.write(s.as_bytes());
+    }
+
+    fn finish(&self) -> u64 {
+        u64::from(self.state)
+    }
+}
+
+#[derive(Default, Clone, Copy)]
+pub struct BuildAocHasher;
+
+impl BuildHasher for BuildAocHasher {
+    type Hasher = AocHasher;
+
+    fn build_hasher(&self) -> AocHasher {
+        AocHasher::default()
+    }
+}
+
+/// A map with one box per HASH value. Each box keeps its entries in
+/// insertion order, and replacing a value leaves the entry where it was.
+pub struct AoCHashMap<K, V> {
+    boxes: [AocBox<K, V>; 256],
+    len: usize,
+}
+
+impl<K: Hash + Eq, V> AoCHashMap<K, V> {
+    pub fn new() -> Self {
+        let boxes: [AocBox<K, V>; 256] = std::array::from_fn(|_| AocBox::new());
+        AoCHashMap { boxes, len: 0 }
+    }
+
+    fn box_idx<Q: Hash + ?Sized>(key: &Q) -> usize {
+        BuildAocHasher.hash_one(key) as usize
+    }
+
+    /// Returns the previous value if `key` was already present
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let aoc_box = &mut self.boxes[Self::box_idx(&key)];
+        let result = aoc_box.insert(key, value);
+        if result.is_none() {
+            self.len += 1;
+        }
+        result
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let aoc_box = &mut self.boxes[Self::box_idx(key)];
+        let result = aoc_box.remove(key);
+        if result.is_some() {
+            self.len -= 1;
+        }
+        result
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.boxes[Self::box_idx(key)].get(key)
     }
 
-    fn insert(&mut self, label: String, focal_length: usize) {
-        let box_idx = usize::from(hash(label.clone()));
-        let aoc_box = self.boxes.get_mut(box_idx).unwrap();
-        aoc_box.insert(label, focal_length);
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.boxes[Self::box_idx(key)].get_mut(key)
     }
 
-    fn remove(&mut self, label: String) {
-        let box_idx = usize::from(hash(label.clone()));
-        let aoc_box = self.boxes.get_mut(box_idx).unwrap();
-        aoc_box.remove(label);
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get(key).is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Entries in box order, and in insertion order within each box
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.boxes
+            .iter()
+            .flat_map(|aoc_box| aoc_box.entries.iter().map(|(k, v)| (k, v)))
     }
+}
 
+impl AoCHashMap<String, usize> {
     fn focal_length_sum(&self) -> usize {
         self.boxes
             .iter()
@@ -77,57 +175,99 @@ impl AoCHashMap {
     }
 }
 
+impl<K: Hash + Eq, V> Default for AoCHashMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Hash + Eq, V> FromIterator<(K, V)> for AoCHashMap<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut result = Self::new();
+        for (key, value) in iter {
+            result.insert(key, value);
+        }
+        result
+    }
+}
+
+impl<K, Q, V> Index<&Q> for AoCHashMap<K, V>
+where
+    K: Hash + Eq + Borrow<Q>,
+    Q: Hash + Eq + ?Sized,
+{
+    type Output = V;
+
+    fn index(&self, key: &Q) -> &V {
+        self.get(key).expect("no entry found for key")
+    }
+}
+
 #[derive(Debug)]
-struct AocBox {
-    lenses: LinkedList<Lens>,
+struct AocBox<K, V> {
+    entries: Vec<(K, V)>,
 }
 
-impl AocBox {
+impl<K: Eq, V> AocBox<K, V> {
     fn new() -> Self {
         AocBox {
-            lenses: LinkedList::new(),
+            entries: Vec::new(),
         }
     }
 
-    fn insert(&mut self, label: String, focal_length: usize) {
-        let mut matching_entries = self.lenses.iter_mut().filter(|lens| lens.label == label);
-        if let Some(lens) = matching_entries.next() {
-            lens.focal_length = focal_length;
-        } else {
-            self.lenses.push_back(Lens {
-                label: label,
-                focal_length: focal_length,
-            });
-        }
+    fn position<Q>(&self, key: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        self.entries.iter().position(|(k, _)| k.borrow() == key)
     }
 
-    fn remove(&mut self, label: String) {
-        let mut matching_entry_idx = self
-            .lenses
-            .iter()
-            .enumerate()
-            .filter(|(idx, lens)| lens.label == label)
-            .map(|(idx, _)| idx);
-        if let Some(entry_idx) = matching_entry_idx.next() {
-            self.lenses.remove(entry_idx);
+    fn insert(&mut self, key: K, value: V) -> Option<V> {
+        match self.position(&key) {
+            Some(idx) => Some(std::mem::replace(&mut self.entries[idx].1, value)),
+            None => {
+                self.entries.push((key, value));
+                None
+            }
         }
     }
 
+    fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        self.position(key).map(|idx| self.entries.remove(idx).1)
+    }
+
+    fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        self.position(key).map(|idx| &self.entries[idx].1)
+    }
+
+    fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        self.position(key).map(|idx| &mut self.entries[idx].1)
+    }
+}
+
+impl<K> AocBox<K, usize> {
     fn focal_lengths(&self) -> usize {
-        self.lenses
+        self.entries
             .iter()
             .enumerate()
-            .map(|(idx, lens)| (idx + 1) * lens.focal_length)
+            .map(|(idx, (_, focal_length))| (idx + 1) * focal_length)
             .sum()
     }
 }
 
-#[derive(Debug)]
-struct Lens {
-    label: String,
-    focal_length: usize,
-}
-
 #[derive(Debug, PartialEq, Eq)]
 enum HashMapInstruction {
     Insert(String, usize),
@@ -151,6 +291,8 @@ impl HashMapInstruction {
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
+
     use super::*;
 
     #[test]
@@ -170,4 +312,96 @@ mod tests {
             HashMapInstruction::Remove("qp".to_string())
         );
     }
+
+    #[test]
+    fn test_part_1() {
+        assert_eq!(part_1(AocBufReader::from_string("inputs/test.txt")), 1320);
+    }
+
+    #[test]
+    fn test_part_2() {
+        assert_eq!(part_2(AocBufReader::from_string("inputs/test.txt")), 145);
+    }
+
+    #[test]
+    fn test_iter_box_order() {
+        let mut hash_map: AoCHashMap<String, usize> = AoCHashMap::new();
+        for x in "rn=1,cm-,qp=3,cm=2,qp-,pc=4,ot=9,ab=5,pc-,pc=6,ot=7".split(",") {
+            match HashMapInstruction::from_str(x) {
+                HashMapInstruction::Insert(label, focal_length) => {
+                    hash_map.insert(label, focal_length);
+                }
+                HashMapInstruction::Remove(label) => {
+                    hash_map.remove(&label);
+                }
+            }
+        }
+
+        assert_eq!(
+            hash_map
+                .iter()
+                .map(|(label, focal_length)| (label.as_str(), *focal_length))
+                .collect::<Vec<(&str, usize)>>(),
+            vec![("rn", 1), ("cm", 2), ("ot", 7), ("ab", 5), ("pc", 6)]
+        );
+        assert_eq!(hash_map.len(), 5);
+        assert_eq!(hash_map["ot"], 7);
+        assert!(!hash_map.contains_key("qp"));
+    }
+
+    #[test]
+    fn test_matches_std_hash_map() {
+        let mut aoc: AoCHashMap<String, usize> = AoCHashMap::new();
+        let mut std: HashMap<String, usize> = HashMap::new();
+
+        // a small LCG is plenty to shuffle a few hundred operations
+        let mut seed: u64 = 0x2023;
+        let mut next = || {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+            (seed >> 33) as usize
+        };
+
+        for _ in 0..2000 {
+            let key = format!("k{}", next() % 300);
+            match next() % 4 {
+                0 => assert_eq!(aoc.remove(&key), std.remove(&key)),
+                1 => {
+                    if let Some(v) = aoc.get_mut(&key) {
+                        *v += 1;
+                    }
+                    if let Some(v) = std.get_mut(&key) {
+                        *v += 1;
+                    }
+                }
+                _ => {
+                    let value = next();
+                    assert_eq!(aoc.insert(key.clone(), value), std.insert(key, value));
+                }
+            }
+            assert_eq!(aoc.len(), std.len());
+        }
+
+        for idx in 0..300 {
+            let key = format!("k{idx}");
+            assert_eq!(aoc.get(&key), std.get(&key));
+            assert_eq!(aoc.contains_key(&key), std.contains_key(&key));
+        }
+        assert_eq!(
+            aoc.iter()
+                .map(|(k, v)| (k.clone(), *v))
+                .collect::<HashMap<String, usize>>(),
+            std
+        );
+    }
+
+    #[test]
+    fn test_std_hash_map_with_aoc_hasher() {
+        let mut hash_map: HashMap<&str, usize, BuildAocHasher> = HashMap::default();
+        hash_map.insert("rn", 1);
+        hash_map.insert("cm", 2);
+
+        assert_eq!(hash_map["rn"], 1);
+        assert_eq!(BuildAocHasher.hash_one("rn"), 0);
+        assert_eq!(BuildAocHasher.hash_one("HASH"), 52);
+    }
 }
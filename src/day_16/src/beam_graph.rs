@@ -0,0 +1,288 @@
+use shared::coords::UCoord;
+use shared::direction::Direction;
+
+use crate::{Beam, LaserTable};
+
+const DIRECTIONS: [Direction; 4] = [
+    Direction::North,
+    Direction::East,
+    Direction::South,
+    Direction::West,
+];
+
+const UNVISITED: usize = usize::MAX;
+
+/// Every beam state on the table as a node, numbered by (tile, side the
+/// beam entered from), with an edge to each state the beam moves into next
+pub struct BeamGraph {
+    n_cols: usize,
+    successors: Vec<Vec<usize>>,
+}
+
+impl BeamGraph {
+    pub fn new(laser_table: &LaserTable) -> Self {
+        let n_cols = laser_table.n_cols;
+        let n_nodes = laser_table.n_rows * n_cols * DIRECTIONS.len();
+        let mut graph = Self {
+            n_cols,
+            successors: vec![Vec::new(); n_nodes],
+        };
+        for node in 0..n_nodes {
+            let beam = graph.beam(node);
+            graph.successors[node] = laser_table
+                .next_beams(&beam)
+                .iter()
+                .map(|next_beam| graph.node(next_beam))
+                .collect();
+        }
+        graph
+    }
+
+    fn node(&self, beam: &Beam) -> usize {
+        let direction_idx = DIRECTIONS
+            .iter()
+            .position(|d| *d == beam.entered_from)
+            .unwrap();
+        (beam.coord.row * self.n_cols + beam.coord.col) * DIRECTIONS.len() + direction_idx
+    }
+
+    fn beam(&self, node: usize) -> Beam {
+        let tile = node / DIRECTIONS.len();
+        Beam::new(
+            UCoord::new(tile / self.n_cols, tile % self.n_cols),
+            DIRECTIONS[node % DIRECTIONS.len()].clone(),
+        )
+    }
+
+    /// The strongly connected components of the graph (Tarjan's algorithm,
+    /// without recursion since beams can loop through thousands of states).
+    /// Components come out in reverse topological order: every component
+    /// comes after all the components it can reach.
+    fn strongly_connected_components(&self) -> Vec<Vec<usize>> {
+        let n_nodes = self.successors.len();
+        let mut index: Vec<usize> = vec![UNVISITED; n_nodes];
+        let mut lowlink: Vec<usize> = vec![0; n_nodes];
+        let mut on_stack: Vec<bool> = vec![false; n_nodes];
+        let mut stack: Vec<usize> = Vec::new();
+        let mut next_index: usize = 0;
+        let mut components: Vec<Vec<usize>> = Vec::new();
+
+        for root in 0..n_nodes {
+            if index[root] != UNVISITED {
+                continue;
+            }
+            // (node, how many of its successors we've looked at)
+            let mut call_stack: Vec<(usize, usize)> = vec![(root, 0)];
+            index[root] = next_index;
+            lowlink[root] = next_index;
+            next_index += 1;
+            stack.push(root);
+            on_stack[root] = true;
+
+            while let Some(&(v, n_seen)) = call_stack.last() {
+                if let Some(&w) = self.successors[v].get(n_seen) {
+                    call_stack.last_mut().unwrap().1 += 1;
+                    if index[w] == UNVISITED {
+                        index[w] = next_index;
+                        lowlink[w] = next_index;
+                        next_index += 1;
+                        stack.push(w);
+                        on_stack[w] = true;
+                        call_stack.push((w, 0));
+                    } else if on_stack[w] {
+                        lowlink[v] = lowlink[v].min(index[w]);
+                    }
+                    continue;
+                }
+
+                call_stack.pop();
+                if let Some(&(parent, _)) = call_stack.last() {
+                    lowlink[parent] = lowlink[parent].min(lowlink[v]);
+                }
+                if lowlink[v] == index[v] {
+                    let mut component: Vec<usize> = Vec::new();
+                    loop {
+                        let w = stack.pop().unwrap();
+                        on_stack[w] = false;
+                        component.push(w);
+                        if w == v {
+                            break;
+                        }
+                    }
+                    components.push(component);
+                }
+            }
+        }
+        components
+    }
+
+    /// The tiles energized by a beam starting in each of `starts`. Beam
+    /// states that loop into each other are condensed into one component,
+    /// and each component's tiles are worked out once, from the tiles of
+    /// the components it leads to, so starts share all their work. A
+    /// component's tiles are dropped once nothing else needs them.
+    pub fn energized(&self, starts: &[Beam]) -> Vec<TileSet> {
+        let n_tiles = self.successors.len() / DIRECTIONS.len();
+        let components = self.strongly_connected_components();
+        let mut component_of: Vec<usize> = vec![0; self.successors.len()];
+        for (component_idx, component) in components.iter().enumerate() {
+            for node in component {
+                component_of[*node] = component_idx;
+            }
+        }
+
+        let start_components: Vec<usize> = starts
+            .iter()
+            .map(|beam| component_of[self.node(beam)])
+            .collect();
+        let mut n_uses: Vec<usize> = vec![0; components.len()];
+        for component_idx in start_components.iter() {
+            n_uses[*component_idx] += 1;
+        }
+        for (node, successors) in self.successors.iter().enumerate() {
+            for successor in successors {
+                if component_of[*successor] != component_of[node] {
+                    n_uses[component_of[*successor]] += 1;
+                }
+            }
+        }
+
+        let mut tiles: Vec<Option<TileSet>> = Vec::with_capacity(components.len());
+        for (component_idx, component) in components.iter().enumerate() {
+            let mut component_tiles = TileSet::new(n_tiles);
+            for node in component {
+                component_tiles.insert(node / DIRECTIONS.len());
+                for successor in self.successors[*node].iter() {
+                    let successor_component = component_of[*successor];
+                    if successor_component == component_idx {
+                        continue;
+                    }
+                    component_tiles.union_with(tiles[successor_component].as_ref().unwrap());
+                    n_uses[successor_component] -= 1;
+                    if n_uses[successor_component] == 0 {
+                        tiles[successor_component] = None;
+                    }
+                }
+            }
+            tiles.push(match n_uses[component_idx] {
+                0 => None,
+                _ => Some(component_tiles),
+            });
+        }
+
+        start_components
+            .into_iter()
+            .map(|component_idx| tiles[component_idx].clone().unwrap())
+            .collect()
+    }
+}
+
+/// A set of tiles, by row-major index, as a bitset
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TileSet {
+    words: Vec<u64>,
+}
+
+impl TileSet {
+    fn new(n_tiles: usize) -> Self {
+        Self {
+            words: vec![0; n_tiles.div_ceil(64)],
+        }
+    }
+
+    fn insert(&mut self, tile: usize) {
+        self.words[tile / 64] |= 1 << (tile % 64);
+    }
+
+    fn union_with(&mut self, other: &TileSet) {
+        for (word, other_word) in self.words.iter_mut().zip(other.words.iter()) {
+            *word |= other_word;
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
+    }
+}
+
+impl LaserTable {
+    /// Every way a beam can enter the table from its edge
+    pub fn entry_points(&self) -> Vec<Beam> {
+        let mut result: Vec<Beam> = Vec::new();
+        for col in 0..self.n_cols {
+            result.push(Beam::new(UCoord::new(0, col), Direction::North));
+            result.push(Beam::new(
+                UCoord::new(self.n_rows - 1, col),
+                Direction::South,
+            ));
+        }
+        for row in 0..self.n_rows {
+            result.push(Beam::new(UCoord::new(row, 0), Direction::West));
+            result.push(Beam::new(
+                UCoord::new(row, self.n_cols - 1),
+                Direction::East,
+            ));
+        }
+        result
+    }
+
+    /// Every entry point and how many tiles a beam entering there
+    /// energizes, most energized first
+    pub fn rank_entry_points(&self) -> Vec<(Beam, usize)> {
+        let entry_points = self.entry_points();
+        let energized = BeamGraph::new(self).energized(&entry_points);
+        let mut result: Vec<(Beam, usize)> = entry_points
+            .into_iter()
+            .zip(energized.iter().map(|tiles| tiles.len()))
+            .collect();
+        result.sort_by_key(|(_, n_energized)| std::cmp::Reverse(*n_energized));
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::input::AocBufReader;
+
+    #[test]
+    fn test_energized_matches_energize() {
+        let laser_table = LaserTable::from_reader(AocBufReader::from_string("inputs/test.txt"));
+        let entry_points = laser_table.entry_points();
+        let energized = BeamGraph::new(&laser_table).energized(&entry_points);
+
+        for (beam, tiles) in entry_points.into_iter().zip(energized) {
+            let mut fresh_table =
+                LaserTable::from_reader(AocBufReader::from_string("inputs/test.txt"));
+            fresh_table.add_beam(beam);
+            fresh_table.energize();
+            assert_eq!(tiles.len(), fresh_table.n_energized());
+        }
+    }
+
+    #[test]
+    fn test_rank_entry_points() {
+        let laser_table = LaserTable::from_reader(AocBufReader::from_string("inputs/test.txt"));
+        let ranking = laser_table.rank_entry_points();
+        assert_eq!(ranking.len(), 40);
+        assert_eq!(
+            ranking[0],
+            (Beam::new(UCoord::new(0, 3), Direction::North), 51)
+        );
+        assert!(ranking.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+        assert!(ranking.contains(&(Beam::new(UCoord::new(0, 0), Direction::West), 46)));
+    }
+
+    #[test]
+    fn test_strongly_connected_components() {
+        // 0 -> 1 -> 2 -> 0 is a loop, 4 leads to 3, which leads nowhere
+        let graph = BeamGraph {
+            n_cols: 1,
+            successors: vec![vec![1], vec![2], vec![0], vec![], vec![3]],
+        };
+        assert_eq!(
+            graph.strongly_connected_components(),
+            vec![vec![2, 1, 0], vec![3], vec![4]]
+        );
+    }
+}
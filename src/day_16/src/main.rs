@@ -4,7 +4,27 @@ use shared::coords::UCoord;
 use shared::direction::Direction;
 use shared::input::AocBufReader;
 
+mod beam_graph;
+mod optics;
+use crate::optics::Optics;
+
 fn main() {
+    if std::env::args().nth(1).as_deref() == Some("--rank") {
+        let mut laser_table =
+            LaserTable::from_reader(AocBufReader::from_string("inputs/part_1.txt"));
+        let ranking = laser_table.rank_entry_points();
+        for (beam, n_energized) in ranking.iter() {
+            println!(
+                "{},{} from the {:?}: {n_energized}",
+                beam.coord.row, beam.coord.col, beam.entered_from
+            );
+        }
+        laser_table.add_beam(ranking[0].0.clone());
+        laser_table.energize();
+        laser_table.print_energized();
+        return;
+    }
+
     let result = part_1(AocBufReader::from_string("inputs/part_1.txt"));
     println!("part 1: {result}");
 
@@ -20,49 +40,8 @@ fn part_1(reader: AocBufReader) -> usize {
 }
 
 fn part_2(reader: AocBufReader) -> usize {
-    let mut laser_table = LaserTable::from_reader(reader);
-    let mut maximum_value: usize = 0;
-
-    let n_rows = laser_table.n_rows;
-    let n_cols = laser_table.n_cols;
-
-    for col in 0..n_cols {
-        laser_table.add_beam(Beam::new(UCoord::new(0, col), Direction::North));
-        laser_table.energize();
-        let n_energized = laser_table.n_energized();
-        if n_energized > maximum_value {
-            maximum_value = n_energized
-        }
-        laser_table.reset();
-
-        laser_table.add_beam(Beam::new(UCoord::new(n_rows - 1, col), Direction::South));
-        laser_table.energize();
-        let n_energized = laser_table.n_energized();
-        if n_energized > maximum_value {
-            maximum_value = n_energized
-        }
-        laser_table.reset();
-    }
-
-    for row in 0..n_rows {
-        laser_table.add_beam(Beam::new(UCoord::new(row, 0), Direction::West));
-        laser_table.energize();
-        let n_energized = laser_table.n_energized();
-        if n_energized > maximum_value {
-            maximum_value = n_energized
-        }
-        laser_table.reset();
-
-        laser_table.add_beam(Beam::new(UCoord::new(row, n_cols - 1), Direction::East));
-        laser_table.energize();
-        let n_energized = laser_table.n_energized();
-        if n_energized > maximum_value {
-            maximum_value = n_energized
-        }
-        laser_table.reset();
-    }
-
-    maximum_value
+    let laser_table = LaserTable::from_reader(reader);
+    laser_table.rank_entry_points()[0].1
 }
 
 struct LaserTable {
@@ -71,12 +50,22 @@ struct LaserTable {
     n_cols: usize,
     visited_beam_states: HashSet<Beam>, // tile and the previous beam direction
     beams: Vec<Beam>,
+    optics: Optics,
 }
 
 impl LaserTable {
     fn from_reader(reader: AocBufReader) -> Self {
-        let map = reader
-            .into_iter()
+        Self::from_reader_with(reader, Optics::default())
+    }
+
+    /// A table whose tiles behave as `optics` says, e.g. with extra kinds of
+    /// tile added by `Optics::register`
+    fn from_reader_with(reader: AocBufReader, optics: Optics) -> Self {
+        Self::from_lines(reader.into_iter(), optics)
+    }
+
+    fn from_lines(lines: impl Iterator<Item = String>, optics: Optics) -> Self {
+        let map = lines
             .map(|line| line.chars().collect::<Vec<char>>())
             .collect::<Vec<Vec<char>>>();
 
@@ -84,11 +73,12 @@ impl LaserTable {
         let n_cols = map[0].len();
 
         Self {
-            map: map,
-            n_rows: n_rows,
-            n_cols: n_cols,
+            map,
+            n_rows,
+            n_cols,
             visited_beam_states: HashSet::new(),
             beams: Vec::new(),
+            optics,
         }
     }
 
@@ -105,11 +95,6 @@ impl LaserTable {
         }
     }
 
-    fn reset(&mut self) {
-        self.visited_beam_states = HashSet::new();
-        self.beams = Vec::new();
-    }
-
     fn n_energized(&self) -> usize {
         self.energized_squares().len()
     }
@@ -138,6 +123,25 @@ impl LaserTable {
         }
     }
 
+    /// The beam states a beam in state `beam` moves into next, leaving out
+    /// any that would be off the table
+    fn next_beams(&self, beam: &Beam) -> Vec<Beam> {
+        let tile = self.map[beam.coord.row][beam.coord.col];
+        let headings = self.optics.outgoing(tile, &beam.entered_from.reverse());
+
+        headings
+            .iter()
+            .filter_map(|heading| {
+                let coord = beam.coord.neighbor_by_dir(heading)?;
+                if coord.row < self.n_rows && coord.col < self.n_cols {
+                    Some(Beam::new(coord, heading.reverse()))
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
     /// Propagate all beams in self! If a new beam state is visited,
     /// return true. When all beams either leave the table or enter
     /// a state that has already been visisted, return false; we are done
@@ -157,224 +161,27 @@ impl LaserTable {
     }
 
     /// Propagate the beam at self.beams[beam_idx] mutating in place.
-    /// If the beam encounters a splitter, we may mutate self by
-    /// adding a _new_ beam to keep track of. If we add a new beam
-    /// or the subject beam reaches a new beam state return true else false.
+    /// If the beam is split, we may mutate self by adding _new_ beams
+    /// to keep track of. If the beam reaches any new beam state return
+    /// true, else false: it has been absorbed, left the table or
+    /// joined a path we've already traced.
     fn propagate_beam(&mut self, beam_idx: usize) -> bool {
-        let mut beam_to_add: Option<Beam> = None;
-        let mut new_beam_state = false;
-
-        let beam = self.beams.get_mut(beam_idx).unwrap();
-
-        let mirror_char = self.map[beam.coord.row][beam.coord.col];
-
-        match (&beam.entered_from, mirror_char) {
-            // coming from the west
-            (Direction::West, '.') | (Direction::West, '-') => {
-                let next_beam = Beam::new(beam.coord.east().unwrap(), Direction::West);
-                if !self.visited_beam_states.contains(&next_beam)
-                    && next_beam.coord.col < self.n_cols
-                {
-                    beam.update(&next_beam);
-                    self.visited_beam_states.insert(next_beam);
-                    new_beam_state = true;
-                }
-            }
-            (Direction::West, '/') => {
-                if let Some(north) = beam.coord.north() {
-                    let next_beam = Beam::new(north, Direction::South);
-                    if !self.visited_beam_states.contains(&next_beam) {
-                        beam.update(&next_beam);
-                        self.visited_beam_states.insert(next_beam);
-                        new_beam_state = true;
-                    }
-                }
-            }
-            (Direction::West, '\\') => {
-                let next_beam = Beam::new(beam.coord.south().unwrap(), Direction::North);
-                if !self.visited_beam_states.contains(&next_beam)
-                    && next_beam.coord.row < self.n_rows
-                {
-                    beam.update(&next_beam);
-                    self.visited_beam_states.insert(next_beam);
-                    new_beam_state = true;
-                }
-            }
-            (Direction::West, '|') => {
-                if let Some(north) = beam.coord.north() {
-                    let north_beam_new = Beam::new(north, Direction::South);
-                    if !self.visited_beam_states.contains(&north_beam_new) {
-                        beam_to_add = Some(north_beam_new);
-                    }
-                }
-                let next_beam = Beam::new(beam.coord.south().unwrap(), Direction::North);
-                if !self.visited_beam_states.contains(&next_beam)
-                    && next_beam.coord.row < self.n_rows
-                {
-                    beam.update(&next_beam);
-                    self.visited_beam_states.insert(next_beam);
-                    new_beam_state = true;
-                }
-            }
-
-            // coming from the north
-            (Direction::North, '.') | (Direction::North, '|') => {
-                let next_beam = Beam::new(beam.coord.south().unwrap(), Direction::North);
-                if !self.visited_beam_states.contains(&next_beam)
-                    && next_beam.coord.row < self.n_rows
-                {
-                    beam.update(&next_beam);
-                    self.visited_beam_states.insert(next_beam);
-                    new_beam_state = true;
-                }
-            }
-            (Direction::North, '/') => {
-                if let Some(west) = beam.coord.west() {
-                    let next_beam = Beam::new(west, Direction::East);
-                    if !self.visited_beam_states.contains(&next_beam) {
-                        beam.update(&next_beam);
-                        self.visited_beam_states.insert(next_beam);
-                        new_beam_state = true;
-                    }
-                }
-            }
-            (Direction::North, '\\') => {
-                let next_beam = Beam::new(beam.coord.east().unwrap(), Direction::West);
-                if !self.visited_beam_states.contains(&next_beam)
-                    && next_beam.coord.col < self.n_cols
-                {
-                    beam.update(&next_beam);
-                    self.visited_beam_states.insert(next_beam);
-                    new_beam_state = true;
-                }
-            }
-            (Direction::North, '-') => {
-                if let Some(west) = beam.coord.west() {
-                    // we've split and reflected back! We'll maybe add a new beam!
-                    let west_beam_new = Beam::new(west, Direction::East);
-                    if !self.visited_beam_states.contains(&west_beam_new) {
-                        beam_to_add = Some(west_beam_new)
-                    }
-                }
-                let next_beam = Beam::new(beam.coord.east().unwrap(), Direction::West);
-                if !self.visited_beam_states.contains(&next_beam)
-                    && next_beam.coord.col < self.n_cols
-                {
-                    beam.update(&next_beam);
-                    self.visited_beam_states.insert(next_beam);
-                    new_beam_state = true;
-                }
-            }
-
-            // coming from the east
-            (Direction::East, '.') | (Direction::East, '-') => {
-                if let Some(west) = beam.coord.west() {
-                    let next_beam = Beam::new(west, Direction::East);
-                    if !self.visited_beam_states.contains(&next_beam) {
-                        beam.update(&next_beam);
-                        self.visited_beam_states.insert(next_beam);
-                        new_beam_state = true;
-                    }
-                }
-            }
-            (Direction::East, '/') => {
-                let next_beam = Beam::new(beam.coord.south().unwrap(), Direction::North);
-                if !self.visited_beam_states.contains(&next_beam)
-                    && next_beam.coord.row < self.n_rows
-                {
-                    beam.update(&next_beam);
-                    self.visited_beam_states.insert(next_beam);
-                    new_beam_state = true;
-                }
-            }
-            (Direction::East, '\\') => {
-                if let Some(north) = beam.coord.north() {
-                    let next_beam = Beam::new(north, Direction::South);
-                    if !self.visited_beam_states.contains(&next_beam) {
-                        beam.update(&next_beam);
-                        self.visited_beam_states.insert(next_beam);
-                        new_beam_state = true;
-                    }
-                }
-            }
-            (Direction::East, '|') => {
-                if let Some(north) = beam.coord.north() {
-                    let north_new_beam = Beam::new(north, Direction::South);
-                    if !self.visited_beam_states.contains(&north_new_beam) {
-                        beam_to_add = Some(north_new_beam);
-                    }
-                }
-                let next_beam = Beam::new(beam.coord.south().unwrap(), Direction::North);
-                if !self.visited_beam_states.contains(&next_beam)
-                    && next_beam.coord.row < self.n_rows
-                {
-                    beam.update(&next_beam);
-                    self.visited_beam_states.insert(next_beam);
-                    new_beam_state = true;
-                }
-            }
-
-            // coming from the south
-            (Direction::South, '.') | (Direction::South, '|') => {
-                if let Some(north) = beam.coord.north() {
-                    let next_beam = Beam::new(north, Direction::South);
-                    if !self.visited_beam_states.contains(&next_beam) {
-                        beam.update(&next_beam);
-                        self.visited_beam_states.insert(next_beam);
-                        new_beam_state = true;
-                    }
-                }
-            }
-            (Direction::South, '/') => {
-                let next_beam = Beam::new(beam.coord.east().unwrap(), Direction::West);
-                if !self.visited_beam_states.contains(&next_beam)
-                    && next_beam.coord.col < self.n_cols
-                {
-                    beam.update(&next_beam);
-                    self.visited_beam_states.insert(next_beam);
-                    new_beam_state = true;
-                }
-            }
-            (Direction::South, '\\') => {
-                if let Some(west) = beam.coord.west() {
-                    let next_beam = Beam::new(west, Direction::East);
-                    if !self.visited_beam_states.contains(&next_beam) {
-                        beam.update(&next_beam);
-                        self.visited_beam_states.insert(next_beam);
-                        new_beam_state = true;
-                    }
-                }
-            }
-            (Direction::South, '-') => {
-                if let Some(west) = beam.coord.west() {
-                    // we've split and reflected back! We'll maybe add a new beam!
-                    let west_beam_new = Beam::new(west, Direction::East);
-                    if !self.visited_beam_states.contains(&west_beam_new) {
-                        beam_to_add = Some(west_beam_new)
-                    }
-                }
-                let next_beam = Beam::new(beam.coord.east().unwrap(), Direction::West);
-                if !self.visited_beam_states.contains(&next_beam)
-                    && next_beam.coord.col < self.n_cols
-                {
-                    beam.update(&next_beam);
-                    self.visited_beam_states.insert(next_beam);
-                    new_beam_state = true;
-                }
-            }
-
-            _ => panic!(
-                "Unexpected beam state - char: {}, entered_from: {:?}",
-                mirror_char, beam.entered_from
-            ),
+        let mut new_beams: Vec<Beam> = self
+            .next_beams(&self.beams[beam_idx])
+            .into_iter()
+            .filter(|beam| !self.visited_beam_states.contains(beam))
+            .collect();
+        if new_beams.is_empty() {
+            return false;
         }
 
-        if let Some(new_beam) = beam_to_add {
+        let next_beam = new_beams.remove(0);
+        self.visited_beam_states.insert(next_beam.clone());
+        self.beams[beam_idx] = next_beam;
+        for new_beam in new_beams {
             self.add_beam(new_beam);
-            new_beam_state = true
         }
-
-        new_beam_state
+        true
     }
 }
 
@@ -387,13 +194,19 @@ struct Beam {
 impl Beam {
     fn new(coord: UCoord, entered_from: Direction) -> Self {
         Self {
-            coord: coord,
-            entered_from: entered_from,
+            coord,
+            entered_from,
         }
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    fn update(&mut self, other: &Beam) {
-        self.coord = other.coord.clone();
-        self.entered_from = other.entered_from.clone();
+    #[test]
+    fn test_parts() {
+        assert_eq!(part_1(AocBufReader::from_string("inputs/test.txt")), 46);
+        assert_eq!(part_2(AocBufReader::from_string("inputs/test.txt")), 51);
     }
 }
@@ -0,0 +1,179 @@
+use std::collections::HashMap;
+
+use shared::direction::Direction;
+
+const CARDINAL_DIRECTIONS: [Direction; 4] = [
+    Direction::North,
+    Direction::East,
+    Direction::South,
+    Direction::West,
+];
+
+/// How each kind of tile redirects a beam: for a tile and the direction a
+/// beam is heading as it enters it, the directions the beam heads out in.
+/// A tile with no outgoing directions absorbs the beam.
+pub struct Optics {
+    table: HashMap<(char, Direction), Vec<Direction>>,
+}
+
+impl Default for Optics {
+    /// Empty space, splitters and mirrors, as in the puzzle
+    fn default() -> Self {
+        let mut optics = Self {
+            table: HashMap::new(),
+        };
+        optics.register('.', |heading| vec![heading.clone()]);
+        optics.register('-', |heading| match heading {
+            Direction::East | Direction::West => vec![heading.clone()],
+            _ => vec![Direction::East, Direction::West],
+        });
+        optics.register('|', |heading| match heading {
+            Direction::North | Direction::South => vec![heading.clone()],
+            _ => vec![Direction::North, Direction::South],
+        });
+        // a mirror swaps north with east and south with west, or the
+        // other way round
+        optics.register('/', |heading| match heading {
+            Direction::North => vec![Direction::East],
+            Direction::East => vec![Direction::North],
+            Direction::South => vec![Direction::West],
+            _ => vec![Direction::South],
+        });
+        optics.register('\\', |heading| match heading {
+            Direction::North => vec![Direction::West],
+            Direction::West => vec![Direction::North],
+            Direction::South => vec![Direction::East],
+            _ => vec![Direction::South],
+        });
+        optics
+    }
+}
+
+impl Optics {
+    /// Set what `tile` does to a beam heading `heading`, adding a new kind of
+    /// tile or overriding one of the standard ones
+    pub fn insert(&mut self, tile: char, heading: Direction, outgoing: &[Direction]) {
+        self.table.insert((tile, heading), outgoing.to_vec());
+    }
+
+    /// Add a new kind of tile, or replace an existing one, with `outgoing`
+    /// giving the headings out for each of the four headings in
+    pub fn register(&mut self, tile: char, outgoing: impl Fn(&Direction) -> Vec<Direction>) {
+        for heading in CARDINAL_DIRECTIONS {
+            let result = outgoing(&heading);
+            self.insert(tile, heading, &result);
+        }
+    }
+
+    pub fn outgoing(&self, tile: char, heading: &Direction) -> &[Direction] {
+        match self.table.get(&(tile, heading.clone())) {
+            Some(outgoing) => outgoing,
+            None => panic!(
+                "Unexpected beam state - char: {}, heading: {:?}",
+                tile, heading
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Beam, LaserTable};
+    use shared::coords::UCoord;
+
+    /// The tiles a beam entering the top left corner heading east energizes
+    fn energized(rows: &[&str], optics: Optics) -> Vec<String> {
+        let mut laser_table = LaserTable::from_lines(rows.iter().map(|r| r.to_string()), optics);
+        laser_table.add_beam(Beam::new(UCoord::new(0, 0), Direction::West));
+        laser_table.energize();
+        let energized_squares = laser_table.energized_squares();
+        (0..laser_table.n_rows)
+            .map(|row| {
+                (0..laser_table.n_cols)
+                    .map(
+                        |col| match energized_squares.contains(&UCoord::new(row, col)) {
+                            true => '#',
+                            false => '.',
+                        },
+                    )
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_standard_elements() {
+        let optics = Optics::default();
+        assert_eq!(optics.outgoing('.', &Direction::South), [Direction::South]);
+        assert_eq!(
+            optics.outgoing('|', &Direction::West),
+            [Direction::North, Direction::South]
+        );
+        assert_eq!(optics.outgoing('-', &Direction::West), [Direction::West]);
+        assert_eq!(optics.outgoing('/', &Direction::East), [Direction::North]);
+        assert_eq!(optics.outgoing('\\', &Direction::East), [Direction::South]);
+
+        assert_eq!(
+            energized(&[".\\.", "./.", "..."], Optics::default()),
+            vec!["##.", "##.", "..."]
+        );
+        assert_eq!(
+            energized(&["..|..", ".....", "\\.-./"], Optics::default()),
+            vec!["###.#", "#.#.#", "#####"]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Unexpected beam state - char: #")]
+    fn test_unknown_tile() {
+        energized(&[".#."], Optics::default());
+    }
+
+    #[test]
+    fn test_absorber() {
+        let mut optics = Optics::default();
+        optics.register('#', |_| vec![]);
+        assert_eq!(
+            energized(&["..#..", "....."], optics),
+            vec!["###..", "....."]
+        );
+    }
+
+    #[test]
+    fn test_one_way_mirror() {
+        // reflects beams heading east down, lets beams heading west through
+        let one_way_optics = || {
+            let mut optics = Optics::default();
+            optics.register('>', |heading| match heading {
+                Direction::East => vec![Direction::South],
+                other => vec![other.clone()],
+            });
+            optics
+        };
+        assert_eq!(
+            energized(&[".>.", "...", "\\/."], one_way_optics()),
+            vec!["##.", "##.", "##."]
+        );
+        assert_eq!(
+            energized(&["..\\", "...", ".>/"], one_way_optics()),
+            vec!["###", "..#", "###"]
+        );
+    }
+
+    #[test]
+    fn test_prism() {
+        // splits a beam into one carrying straight on and one to each side
+        let mut optics = Optics::default();
+        optics.register('Y', |heading| match heading {
+            Direction::North | Direction::South => {
+                vec![heading.clone(), Direction::East, Direction::West]
+            }
+            _ => vec![heading.clone(), Direction::North, Direction::South],
+        });
+        assert_eq!(
+            energized(&["\\..", "\\Y.", "..."], optics),
+            vec!["##.", "###", ".#."]
+        );
+    }
+}
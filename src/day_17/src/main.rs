@@ -1,71 +1,88 @@
+#[cfg(test)]
 use std::collections::{HashMap, HashSet};
 
 use shared::coords::UCoord;
 use shared::direction::Direction;
 use shared::input::AocBufReader;
 
+mod movement;
+mod search;
+use crate::movement::{Crucible, MovementRule, TurnPenalty, UltraCrucible};
+use crate::search::{search, Heuristic};
+
 const USIZE_RADIX: u32 = 10;
 
 fn main() {
-    let result = part_1(AocBufReader::from_string("inputs/part_1.txt"));
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let heuristic = match args.iter().any(|arg| arg == "--no-heuristic") {
+        true => Heuristic::Zero,
+        false => Heuristic::Manhattan,
+    };
+
+    if args.iter().any(|arg| arg == "--render") {
+        let map = HeatLossMap::from_reader(AocBufReader::from_string("inputs/part_1.txt"));
+        let end = UCoord::new(map.n_rows - 1, map.n_cols - 1);
+        let penalized = TurnPenalty {
+            rule: Crucible,
+            penalty: 10,
+        };
+        let rules: [(&str, &dyn MovementRule); 3] = [
+            ("crucible", &Crucible),
+            ("ultra crucible", &UltraCrucible),
+            ("crucible, 10 per turn", &penalized),
+        ];
+        for (name, rule) in rules {
+            let (cost, route) =
+                search(UCoord::new(0, 0), end.clone(), &map, rule, heuristic).unwrap();
+            println!("{name}: {cost}\n{}\n", map.render_route(&route));
+        }
+        return;
+    }
+
+    let result = part_1(AocBufReader::from_string("inputs/part_1.txt"), heuristic);
     println!("part 1: {result}");
 
-    let result = part_2(AocBufReader::from_string("inputs/part_1.txt"));
+    let result = part_2(AocBufReader::from_string("inputs/part_1.txt"), heuristic);
     println!("part 2: {result}")
 }
 
-fn part_1(reader: AocBufReader) -> usize {
+fn part_1(reader: AocBufReader, heuristic: Heuristic) -> usize {
     let heat_loss_map = HeatLossMap::from_reader(reader);
     let start = UCoord::new(0, 0);
     let end = UCoord::new(heat_loss_map.n_rows - 1, heat_loss_map.n_cols - 1);
-    dijkstra(start, end, &heat_loss_map, 0, 3)
+    search(start, end, &heat_loss_map, &Crucible, heuristic)
+        .unwrap()
+        .0
 }
 
-fn part_2(reader: AocBufReader) -> usize {
+fn part_2(reader: AocBufReader, heuristic: Heuristic) -> usize {
     let heat_loss_map = HeatLossMap::from_reader(reader);
     let start = UCoord::new(0, 0);
     let end = UCoord::new(heat_loss_map.n_rows - 1, heat_loss_map.n_cols - 1);
-    dijkstra(start, end, &heat_loss_map, 4, 10)
+    search(start, end, &heat_loss_map, &UltraCrucible, heuristic)
+        .unwrap()
+        .0
 }
 
+/// The least heat loss from `start` to `end` and the route that achieves
+/// it, from one of the two starting nodes to the end, or None if the end
+/// can't be reached. `rule` decides where the crucible can move and where
+/// it is allowed to stop. This is the straightforward version `search` is
+/// checked against; it is far too slow for the real input.
+#[cfg(test)]
 fn dijkstra(
     start: UCoord,
     end: UCoord,
     map: &HeatLossMap,
-    min_straight_line_distance: usize,
-    max_straight_line_distance: usize,
-) -> usize {
+    rule: &dyn MovementRule,
+) -> Option<(usize, Vec<Node>)> {
+    let start_nodes: Vec<Node> = rule.start_nodes(&start);
+
     let mut visited_nodes: HashSet<Node> = HashSet::new();
-    let mut to_visit: HashSet<Node> = HashSet::from([
-        Node {
-            coord: start.clone(),
-            direction: Direction::East,
-            straight_line_counter: 0,
-        },
-        Node {
-            coord: start.clone(),
-            direction: Direction::South,
-            straight_line_counter: 0,
-        },
-    ]);
-    let mut minimum_cost_to_reach_node: HashMap<Node, usize> = HashMap::from([
-        (
-            Node {
-                coord: start.clone(),
-                direction: Direction::East,
-                straight_line_counter: 0,
-            },
-            0,
-        ),
-        (
-            Node {
-                coord: start,
-                direction: Direction::South,
-                straight_line_counter: 0,
-            },
-            0,
-        ),
-    ]);
+    let mut to_visit: HashSet<Node> = start_nodes.iter().cloned().collect();
+    let mut minimum_cost_to_reach_node: HashMap<Node, usize> =
+        start_nodes.into_iter().map(|node| (node, 0)).collect();
+    let mut predecessors: HashMap<Node, Node> = HashMap::new();
 
     while !to_visit.is_empty() {
         let current_node = to_visit
@@ -77,31 +94,34 @@ fn dijkstra(
         to_visit.remove(&current_node);
         visited_nodes.insert(current_node.clone());
 
-        let next_nodes =
-            current_node.neighbors(&map, min_straight_line_distance, max_straight_line_distance);
-        for next_node in next_nodes {
-            let cost_to_get_to_node = current_cost + map.get(&next_node.coord).unwrap();
-            if next_node.coord == end {
-                return cost_to_get_to_node;
-            } else if !visited_nodes.contains(&next_node) {
-                let current_min_cost = minimum_cost_to_reach_node.get(&next_node);
-                match current_min_cost {
-                    Some(x) => {
-                        if cost_to_get_to_node < *x {
-                            minimum_cost_to_reach_node
-                                .insert(next_node.clone(), cost_to_get_to_node);
-                        }
-                    }
-                    None => {
-                        minimum_cost_to_reach_node.insert(next_node.clone(), cost_to_get_to_node);
-                    }
-                }
-                to_visit.insert(next_node);
+        if current_node.coord == end && rule.can_stop(&current_node) {
+            let mut route: Vec<Node> = vec![current_node];
+            while let Some(previous) = predecessors.get(route.last().unwrap()) {
+                route.push(previous.clone());
             }
+            route.reverse();
+            return Some((current_cost, route));
+        }
+
+        for (next_node, cost) in rule.moves(&current_node, map) {
+            if visited_nodes.contains(&next_node) {
+                continue;
+            }
+
+            let cost_to_get_to_node = current_cost + cost;
+            let improved = match minimum_cost_to_reach_node.get(&next_node) {
+                Some(x) => cost_to_get_to_node < *x,
+                None => true,
+            };
+            if improved {
+                minimum_cost_to_reach_node.insert(next_node.clone(), cost_to_get_to_node);
+                predecessors.insert(next_node.clone(), current_node.clone());
+            }
+            to_visit.insert(next_node);
         }
     }
 
-    panic!("awww! we didn't find the end");
+    None
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -111,68 +131,6 @@ struct Node {
     straight_line_counter: usize,
 }
 
-impl Node {
-    fn neighbors(
-        &self,
-        map: &HeatLossMap,
-        min_straight_line_distance: usize,
-        max_straight_line_distance: usize,
-    ) -> Vec<Node> {
-        if self.straight_line_counter < min_straight_line_distance {
-            // we _must_ go in a straight line!
-            if let Some(neighbor) = self.coord.neighbor_by_dir(&self.direction) {
-                if map.contains(&neighbor) {
-                    return vec![Node {
-                        coord: neighbor,
-                        direction: self.direction.clone(),
-                        straight_line_counter: self.straight_line_counter + 1,
-                    }];
-                }
-            }
-            return vec![];
-        }
-
-        let mut result: Vec<Node> = Vec::new();
-        // we don't _necessarily_ need to go straight and maybe we can't!
-        for direction in [
-            Direction::North,
-            Direction::East,
-            Direction::South,
-            Direction::West,
-        ] {
-            if direction == self.direction {
-                // we can only as many as 10 in a straight line
-                if self.straight_line_counter < max_straight_line_distance {
-                    if let Some(neighbor) = self.coord.neighbor_by_dir(&direction) {
-                        if map.contains(&neighbor) {
-                            result.push(Node {
-                                coord: neighbor,
-                                direction: direction,
-                                straight_line_counter: self.straight_line_counter + 1,
-                            })
-                        }
-                    }
-                }
-            } else if direction == self.direction.reverse() {
-                // we still can't turn around
-                continue;
-            } else {
-                if let Some(neighbor) = self.coord.neighbor_by_dir(&direction) {
-                    if map.contains(&neighbor) {
-                        result.push(Node {
-                            coord: neighbor,
-                            direction: direction,
-                            straight_line_counter: 1,
-                        })
-                    }
-                }
-            }
-        }
-
-        result
-    }
-}
-
 struct HeatLossMap {
     map: Vec<Vec<usize>>,
     n_rows: usize,
@@ -185,7 +143,6 @@ impl HeatLossMap {
             .into_iter()
             .map(|line| {
                 line.chars()
-                    .into_iter()
                     .map(|c| usize::try_from(c.to_digit(USIZE_RADIX).unwrap()).unwrap())
                     .collect::<Vec<usize>>()
             })
@@ -194,9 +151,9 @@ impl HeatLossMap {
         let n_cols = map[0].len();
 
         Self {
-            map: map,
-            n_rows: n_rows,
-            n_cols: n_cols,
+            map,
+            n_rows,
+            n_cols,
         }
     }
 
@@ -211,4 +168,111 @@ impl HeatLossMap {
     fn contains(&self, coord: &UCoord) -> bool {
         coord.row < self.n_rows && coord.col < self.n_cols
     }
+
+    /// The map with every step of `route` drawn as an arrow in the
+    /// direction the crucible moved to get there
+    fn render_route(&self, route: &[Node]) -> String {
+        let mut chars: Vec<Vec<char>> = self
+            .map
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|heat_loss| char::from_digit(*heat_loss as u32, USIZE_RADIX).unwrap())
+                    .collect()
+            })
+            .collect();
+
+        for node in route.iter().filter(|node| node.straight_line_counter > 0) {
+            chars[node.coord.row][node.coord.col] = match node.direction {
+                Direction::North => '^',
+                Direction::East => '>',
+                Direction::South => 'v',
+                Direction::West => '<',
+                _ => panic!("Crucibles only move in cardinal directions"),
+            };
+        }
+
+        chars
+            .into_iter()
+            .map(|row| row.into_iter().collect::<String>())
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::movement::straight_line_moves;
+
+    /// A crucible that has to go at least 20 blocks in a straight line
+    struct Sluggish;
+
+    impl MovementRule for Sluggish {
+        fn moves(&self, node: &Node, map: &HeatLossMap) -> Vec<(Node, usize)> {
+            straight_line_moves(node, map, 20, 30)
+        }
+
+        fn can_stop(&self, node: &Node) -> bool {
+            node.straight_line_counter >= 20
+        }
+
+        fn max_straight_line_distance(&self) -> usize {
+            30
+        }
+    }
+
+    fn check_route(map: &HeatLossMap, cost: usize, route: &[Node], rule: &dyn MovementRule) {
+        assert_eq!(route[0].coord, UCoord::new(0, 0));
+        assert_eq!(
+            route.last().unwrap().coord,
+            UCoord::new(map.n_rows - 1, map.n_cols - 1)
+        );
+        assert!(rule.start_nodes(&route[0].coord).contains(&route[0]));
+        let mut route_cost: usize = 0;
+        for step in route.windows(2) {
+            let (_, step_cost) = rule
+                .moves(&step[0], map)
+                .into_iter()
+                .find(|(node, _)| *node == step[1])
+                .unwrap();
+            route_cost += step_cost;
+        }
+        assert_eq!(route_cost, cost);
+        assert!(rule.can_stop(route.last().unwrap()));
+    }
+
+    #[test]
+    fn test_dijkstra() {
+        let map = HeatLossMap::from_reader(AocBufReader::from_string("inputs/test.txt"));
+        let start = UCoord::new(0, 0);
+        let end = UCoord::new(map.n_rows - 1, map.n_cols - 1);
+
+        let (cost, route) = dijkstra(start.clone(), end.clone(), &map, &Crucible).unwrap();
+        assert_eq!(cost, 102);
+        check_route(&map, cost, &route, &Crucible);
+
+        let (cost, route) = dijkstra(start.clone(), end.clone(), &map, &UltraCrucible).unwrap();
+        assert_eq!(cost, 94);
+        check_route(&map, cost, &route, &UltraCrucible);
+
+        // a crucible that has to go at least 20 in a line can't make it
+        assert!(dijkstra(start, end, &map, &Sluggish).is_none());
+    }
+
+    #[test]
+    fn test_render_route() {
+        let map = HeatLossMap::from_reader(AocBufReader::from_string("inputs/test.txt"));
+        let end = UCoord::new(map.n_rows - 1, map.n_cols - 1);
+        let (_, route) = dijkstra(UCoord::new(0, 0), end, &map, &UltraCrucible).unwrap();
+
+        let rendered = map.render_route(&route);
+        assert!(rendered.starts_with('2'));
+        assert_eq!(
+            rendered.chars().filter(|c| "<>^v".contains(*c)).count(),
+            route.len() - 1
+        );
+        assert_eq!(rendered.lines().count(), map.n_rows);
+        assert!(rendered.ends_with('v') || rendered.ends_with('>'));
+    }
 }
@@ -0,0 +1,218 @@
+use shared::coords::UCoord;
+use shared::direction::Direction;
+
+use crate::{HeatLossMap, Node};
+
+const CARDINAL_DIRECTIONS: [Direction; 4] = [
+    Direction::North,
+    Direction::East,
+    Direction::South,
+    Direction::West,
+];
+
+/// How a crucible is allowed to move across the map. `dijkstra` only ever
+/// asks a rule where a node can go next, so variants like turn penalties,
+/// U-turns, diagonal moves or extra per-cell costs are each a new rule.
+pub trait MovementRule {
+    /// The nodes a crucible at `node` can move to next, and the heat lost
+    /// making each move
+    fn moves(&self, node: &Node, map: &HeatLossMap) -> Vec<(Node, usize)>;
+
+    /// Whether a crucible at `node` is allowed to stop there
+    fn can_stop(&self, node: &Node) -> bool;
+
+    /// The longest run a crucible can make in a straight line, which
+    /// bounds the `straight_line_counter` of any node it reaches
+    fn max_straight_line_distance(&self) -> usize;
+
+    /// The nodes a crucible is in at `start` before it has moved at all
+    fn start_nodes(&self, start: &UCoord) -> Vec<Node> {
+        [Direction::East, Direction::South]
+            .into_iter()
+            .map(|direction| Node {
+                coord: start.clone(),
+                direction,
+                straight_line_counter: 0,
+            })
+            .collect()
+    }
+}
+
+/// A normal crucible: at most 3 blocks in a straight line
+pub struct Crucible;
+
+impl MovementRule for Crucible {
+    fn moves(&self, node: &Node, map: &HeatLossMap) -> Vec<(Node, usize)> {
+        straight_line_moves(node, map, 0, 3)
+    }
+
+    fn can_stop(&self, _node: &Node) -> bool {
+        true
+    }
+
+    fn max_straight_line_distance(&self) -> usize {
+        3
+    }
+}
+
+/// An ultra crucible: at least 4 blocks in a straight line before it can
+/// turn or stop, and at most 10
+pub struct UltraCrucible;
+
+impl MovementRule for UltraCrucible {
+    fn moves(&self, node: &Node, map: &HeatLossMap) -> Vec<(Node, usize)> {
+        straight_line_moves(node, map, 4, 10)
+    }
+
+    fn can_stop(&self, node: &Node) -> bool {
+        node.straight_line_counter >= 4
+    }
+
+    fn max_straight_line_distance(&self) -> usize {
+        10
+    }
+}
+
+/// Any rule, but every change of direction costs an extra `penalty`
+pub struct TurnPenalty<R: MovementRule> {
+    pub rule: R,
+    pub penalty: usize,
+}
+
+impl<R: MovementRule> MovementRule for TurnPenalty<R> {
+    fn moves(&self, node: &Node, map: &HeatLossMap) -> Vec<(Node, usize)> {
+        self.rule
+            .moves(node, map)
+            .into_iter()
+            .map(|(next_node, cost)| {
+                if next_node.direction == node.direction {
+                    (next_node, cost)
+                } else {
+                    (next_node, cost + self.penalty)
+                }
+            })
+            .collect()
+    }
+
+    fn can_stop(&self, node: &Node) -> bool {
+        self.rule.can_stop(node)
+    }
+
+    fn max_straight_line_distance(&self) -> usize {
+        self.rule.max_straight_line_distance()
+    }
+
+    fn start_nodes(&self, start: &UCoord) -> Vec<Node> {
+        self.rule.start_nodes(start)
+    }
+}
+
+/// Moves in the four cardinal directions that never turn around, have gone
+/// at least `min_straight_line_distance` before turning, and at most
+/// `max_straight_line_distance` in a straight line. Each move costs the
+/// heat loss of the block it enters.
+pub fn straight_line_moves(
+    node: &Node,
+    map: &HeatLossMap,
+    min_straight_line_distance: usize,
+    max_straight_line_distance: usize,
+) -> Vec<(Node, usize)> {
+    let mut result: Vec<(Node, usize)> = Vec::new();
+    for direction in CARDINAL_DIRECTIONS {
+        let straight_line_counter = if direction == node.direction {
+            if node.straight_line_counter >= max_straight_line_distance {
+                continue;
+            }
+            node.straight_line_counter + 1
+        } else if direction == node.direction.reverse()
+            || node.straight_line_counter < min_straight_line_distance
+        {
+            // we can't turn around, or turn at all until we've gone far enough
+            continue;
+        } else {
+            1
+        };
+
+        if let Some(coord) = node.coord.neighbor_by_dir(&direction) {
+            if let Some(heat_loss) = map.get(&coord) {
+                result.push((
+                    Node {
+                        coord,
+                        direction,
+                        straight_line_counter,
+                    },
+                    heat_loss,
+                ));
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dijkstra;
+    use shared::input::AocBufReader;
+
+    #[test]
+    fn test_straight_line_moves() {
+        let map = HeatLossMap::from_reader(AocBufReader::from_string("inputs/test.txt"));
+        let node = Node {
+            coord: UCoord::new(1, 1),
+            direction: Direction::East,
+            straight_line_counter: 3,
+        };
+        let coords = |moves: Vec<(Node, usize)>| -> Vec<UCoord> {
+            moves.into_iter().map(|(n, _)| n.coord).collect()
+        };
+
+        // a crucible must turn, an ultra crucible can carry on but not turn
+        assert_eq!(
+            coords(Crucible.moves(&node, &map)),
+            vec![UCoord::new(0, 1), UCoord::new(2, 1)]
+        );
+        assert_eq!(
+            UltraCrucible.moves(&node, &map),
+            vec![(
+                Node {
+                    coord: UCoord::new(1, 2),
+                    direction: Direction::East,
+                    straight_line_counter: 4,
+                },
+                1
+            )]
+        );
+        assert!(!UltraCrucible.can_stop(&node));
+    }
+
+    #[test]
+    fn test_turn_penalty() {
+        let map = HeatLossMap::from_reader(AocBufReader::from_string("inputs/test.txt"));
+        let start = UCoord::new(0, 0);
+        let end = UCoord::new(map.n_rows - 1, map.n_cols - 1);
+
+        let (cost, _) = dijkstra(start.clone(), end.clone(), &map, &Crucible).unwrap();
+        let rule = TurnPenalty {
+            rule: Crucible,
+            penalty: 0,
+        };
+        assert_eq!(
+            dijkstra(start.clone(), end.clone(), &map, &rule).unwrap().0,
+            cost
+        );
+
+        // the route has to turn at least 7 times to get across the map
+        let rule = TurnPenalty {
+            rule: Crucible,
+            penalty: 100,
+        };
+        let (penalized_cost, route) = dijkstra(start, end, &map, &rule).unwrap();
+        let n_turns = route
+            .windows(2)
+            .filter(|step| step[0].direction != step[1].direction)
+            .count();
+        assert!(n_turns >= 7);
+        assert!(penalized_cost >= cost + 100 * n_turns);
+    }
+}
@@ -0,0 +1,75 @@
+use std::collections::HashMap;
+
+use shared::dot::Graph;
+
+use crate::query::Route;
+use crate::{Comparator, Operation, Workflow};
+
+/// Workflows as nodes and their rules as edges, labelled with the rule's
+/// condition. Every rule taken by one of `routes` is highlighted.
+pub fn workflows_graph(workflows: &HashMap<String, Workflow>, routes: &[Route]) -> Graph {
+    let mut ids: Vec<&String> = workflows.keys().collect();
+    ids.sort_by_key(|id| (id.as_str() != "in", id.as_str()));
+
+    let mut graph = Graph::directed();
+    for id in ids.iter() {
+        graph.add_node(id, Some("workflow"));
+    }
+    graph.add_node("A", Some("accept"));
+    graph.add_node("R", Some("reject"));
+
+    let mut rule_edges: HashMap<(&str, usize), usize> = HashMap::new();
+    for id in ids {
+        for (rule_idx, (operation, dest)) in workflows[id].operations.iter().enumerate() {
+            let edge_idx = graph.add_edge(id, dest, Some(rule_label(operation)));
+            rule_edges.insert((id, rule_idx), edge_idx);
+        }
+    }
+
+    for (workflow_id, rule_idx) in routes.iter().flat_map(|route| route.path.iter()) {
+        graph.highlight_edge(rule_edges[&(workflow_id.as_str(), *rule_idx)]);
+    }
+
+    graph
+}
+
+fn rule_label(operation: &Operation) -> String {
+    match operation {
+        Operation::Nullary => "else".to_string(),
+        Operation::Unary(c, Comparator::LessThan(reference)) => format!("{c}<{reference}"),
+        Operation::Unary(c, Comparator::GreaterThan(reference)) => format!("{c}>{reference}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_workflow;
+    use crate::query::query;
+    use crate::XmasPresent;
+
+    #[test]
+    fn test_workflows_graph() {
+        let workflows: HashMap<String, Workflow> = ["two{m<5:A,R}", "in{x>10:two,R}"]
+            .into_iter()
+            .map(|s| parse_workflow(s.to_string()))
+            .map(|w| (w.id.clone(), w))
+            .collect();
+        let accepted = query(XmasPresent::universal(), &workflows).accepted;
+
+        let graph = workflows_graph(&workflows, &accepted);
+        assert_eq!(
+            graph.to_dot(),
+            "digraph {\n\
+             \x20   \"in\" [shape=box, style=filled, fillcolor=lightblue, tooltip=\"workflow\", color=red, penwidth=2];\n\
+             \x20   \"two\" [shape=box, style=filled, fillcolor=lightblue, tooltip=\"workflow\", color=red, penwidth=2];\n\
+             \x20   \"A\" [shape=diamond, style=filled, fillcolor=palegreen, tooltip=\"accept\", color=red, penwidth=2];\n\
+             \x20   \"R\" [shape=hexagon, style=filled, fillcolor=khaki, tooltip=\"reject\"];\n\
+             \x20   \"in\" -> \"two\" [label=\"x>10\", color=red, penwidth=2];\n\
+             \x20   \"in\" -> \"R\" [label=\"else\"];\n\
+             \x20   \"two\" -> \"A\" [label=\"m<5\", color=red, penwidth=2];\n\
+             \x20   \"two\" -> \"R\" [label=\"else\"];\n\
+             }\n"
+        );
+    }
+}
@@ -3,11 +3,22 @@ use std::collections::HashMap;
 use once_cell::sync::Lazy;
 use regex::Regex;
 
+use shared::dot::Format;
+use shared::hyperbox::HyperBox;
 use shared::input::AocBufReader;
-use shared::range::Range;
+
+mod compiled;
+mod graph;
+mod optimize;
+mod query;
+
+use compiled::CompiledWorkflows;
+use graph::workflows_graph;
+use optimize::{optimize, to_puzzle_syntax};
+use query::{query, unreachable_rules};
 
 static WORKFLOW_RE: Lazy<Regex> =
-    Lazy::new(|| Regex::new(r"^(?<id>.*)\{(?<opers>.*),(?<dest>[^,]*)\}$").unwrap());
+    Lazy::new(|| Regex::new(r"^(?<id>[^{]*)\{(?:(?<opers>.*),)?(?<dest>[^,]*)\}$").unwrap());
 
 static OPERATION_RE: Lazy<Regex> = Lazy::new(|| {
     Regex::new(r"^(?<input>[xmas])(?<comparator>[<>])(?<reference>[0-9]*):(?<goto>.*)$").unwrap()
@@ -18,6 +29,36 @@ static XMAS_RE: Lazy<Regex> = Lazy::new(|| {
 });
 
 fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(|arg| arg.as_str()) == Some("--query") {
+        let (workflows, _) = parse_input(AocBufReader::from_string("inputs/part_1.txt"));
+        let constraints = args.get(1).map(|c| c.as_str()).unwrap_or("");
+        let Some(xmas_present) = XmasPresent::from_constraints(constraints) else {
+            println!("no part satisfies {constraints}");
+            return;
+        };
+        let result = query(xmas_present.clone(), &workflows);
+        println!("accepted: {}", result.n_accepted());
+        println!("rejected: {}", result.n_rejected());
+        for (workflow_id, rule_idx) in unreachable_rules(xmas_present, &workflows) {
+            println!("never taken: {workflow_id} rule {rule_idx}");
+        }
+        return;
+    }
+
+    if args.first().map(|arg| arg.as_str()) == Some("--optimize") {
+        let (workflows, _) = parse_input(AocBufReader::from_string("inputs/part_1.txt"));
+        print!("{}", to_puzzle_syntax(&optimize(&workflows)));
+        return;
+    }
+
+    if let Some(format) = Format::from_args() {
+        let (workflows, _) = parse_input(AocBufReader::from_string("inputs/part_1.txt"));
+        let accepted = query(XmasPresent::universal(), &workflows).accepted;
+        print!("{}", workflows_graph(&workflows, &accepted).render(format));
+        return;
+    }
+
     let result = part_1(AocBufReader::from_string("inputs/part_1.txt"));
     println!("part 1: {result}");
 
@@ -27,48 +68,32 @@ fn main() {
 
 fn part_1(reader: AocBufReader) -> usize {
     let (workflows, xmases) = parse_input(reader);
+    let compiled = CompiledWorkflows::compile(&workflows);
 
-    let mut result: usize = 0;
-    for xmas in xmases {
-        if xmas_accepted_part_1(&xmas, &workflows) {
-            result += xmas.total_value()
-        }
-    }
-    result
+    compiled
+        .evaluate(&xmases)
+        .into_iter()
+        .zip(xmases.iter())
+        .filter(|(accepted, _)| *accepted)
+        .map(|(_, xmas)| xmas.total_value())
+        .sum()
 }
 
 fn part_2(reader: AocBufReader) -> usize {
     let (workflows, _) = parse_input(reader);
-    let universal_present = XmasPresent {
-        x: Range {
-            start: 1usize,
-            end: 4_000,
-        },
-        m: Range {
-            start: 1usize,
-            end: 4_000,
-        },
-        a: Range {
-            start: 1usize,
-            end: 4_000,
-        },
-        s: Range {
-            start: 1usize,
-            end: 4_000,
-        },
-    };
+    let universal_present = XmasPresent::universal();
 
-    let accepted_presents = xmas_accepted_part_2(universal_present, &workflows, "in".to_string());
-    accepted_presents
-        .into_iter()
-        .fold(0usize, |acc, m| acc + m.n_xmases())
+    query(universal_present, &workflows).n_accepted()
 }
 
+/// The original workflow interpreter, kept as the reference that
+/// `CompiledWorkflows` is tested and benchmarked against
+#[cfg(test)]
 fn xmas_accepted_part_1(xmas: &Xmas, workflows: &HashMap<String, Workflow>) -> bool {
     let mut workflow_id: String = "in".to_string();
     loop {
         let workflow = workflows.get(&workflow_id).unwrap();
-        let dest = workflow.sort(&xmas);
+        let dest = workflow.sort(xmas);
         if dest == "R" {
             return false;
         } else if dest == "A" {
@@ -79,48 +104,20 @@ fn xmas_accepted_part_1(xmas: &Xmas, workflows: &HashMap<String, Workflow>) -> b
     }
 }
 
-/// return the collection of XmasPresents that were accepted
-fn xmas_accepted_part_2(
-    xmas_present: XmasPresent,
-    workflows: &HashMap<String, Workflow>,
-    workflow_id: String,
-) -> Vec<XmasPresent> {
-    if workflow_id == "A" {
-        return vec![xmas_present];
-    }
-
-    if workflow_id == "R" {
-        return vec![];
-    }
-
-    let workflow = workflows.get(&workflow_id).unwrap();
-    workflow
-        .split(xmas_present)
-        .into_iter()
-        .map(|(present, wid)| xmas_accepted_part_2(present, workflows, wid))
-        .flatten()
-        .collect()
-}
-
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct Workflow {
     id: String,
     operations: Vec<(Operation, String)>,
 }
 
 impl Workflow {
+    #[cfg(test)]
     fn sort(&self, xmas: &Xmas) -> String {
         for (operation, dest) in self.operations.iter() {
             match operation {
                 Operation::Nullary => return dest.clone(),
                 Operation::Unary(c, comparator) => {
-                    let val: usize = match c {
-                        'x' => xmas.x,
-                        'm' => xmas.m,
-                        'a' => xmas.a,
-                        's' => xmas.s,
-                        _ => panic!("Bad operation!"),
-                    };
+                    let val: usize = [xmas.x, xmas.m, xmas.a, xmas.s][xmas_axis(*c)];
                     if comparator.compare(val) {
                         return dest.clone();
                     } else {
@@ -132,24 +129,20 @@ impl Workflow {
         panic!("unreachable! Bad operation")
     }
 
-    fn split(&self, xmas_present: XmasPresent) -> Vec<(XmasPresent, String)> {
+    /// Split a present into the pieces taken by each rule, tagged with the
+    /// index of that rule in `operations`
+    fn split_by_rule(&self, xmas_present: XmasPresent) -> Vec<(XmasPresent, usize)> {
         let mut remainder: Vec<XmasPresent> = vec![xmas_present];
-        let mut shards: Vec<(XmasPresent, String)> = Vec::new();
-        for (operation, dest) in self.operations.iter() {
+        let mut shards: Vec<(XmasPresent, usize)> = Vec::new();
+        for (rule_idx, (operation, _)) in self.operations.iter().enumerate() {
             let mut remainder_: Vec<XmasPresent> = Vec::new();
             for present in remainder {
                 let (to_dest, operation_remainder) = operation.split(present);
-                match operation_remainder {
-                    Some(r) => {
-                        remainder_.push(r);
-                    }
-                    None => (),
+                if let Some(r) = operation_remainder {
+                    remainder_.push(r);
                 }
-                match to_dest {
-                    Some(t) => {
-                        shards.push((t, dest.clone()));
-                    }
-                    None => (),
+                if let Some(t) = to_dest {
+                    shards.push((t, rule_idx));
                 }
             }
             remainder = remainder_;
@@ -158,7 +151,7 @@ impl Workflow {
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 enum Operation {
     Nullary,
     Unary(char, Comparator),
@@ -173,13 +166,14 @@ impl Operation {
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 enum Comparator {
     LessThan(usize),
     GreaterThan(usize),
 }
 
 impl Comparator {
+    #[cfg(test)]
     fn compare(&self, value: usize) -> bool {
         match self {
             Self::LessThan(reference) => value < *reference,
@@ -188,6 +182,7 @@ impl Comparator {
     }
 }
 
+#[derive(Debug)]
 struct Xmas {
     x: usize,
     m: usize,
@@ -205,18 +200,19 @@ impl Xmas {
 /// bounded by ranges of x, m, a, and s
 #[derive(Debug, Clone)]
 struct XmasPresent {
-    x: Range<usize>,
-    m: Range<usize>,
-    a: Range<usize>,
-    s: Range<usize>,
+    bounds: HyperBox<usize, 4>,
 }
 
 impl XmasPresent {
+    /// Every rating combination the puzzle allows: 1..=4000 on each axis
+    fn universal() -> Self {
+        XmasPresent {
+            bounds: HyperBox::new([1; 4], [4_000; 4]).unwrap(),
+        }
+    }
+
     fn n_xmases(&self) -> usize {
-        (self.x.end - self.x.start + 1)
-            * (self.m.end - self.m.start + 1)
-            * (self.a.end - self.a.start + 1)
-            * (self.s.end - self.s.start + 1)
+        self.bounds.volume()
     }
 
     fn split(
@@ -224,116 +220,33 @@ impl XmasPresent {
         c: &char,
         comparator: &Comparator,
     ) -> (Option<XmasPresent>, Option<XmasPresent>) {
-        match (c, comparator) {
-            ('x', Comparator::LessThan(ref_)) => {
-                if self.x.start >= *ref_ {
-                    (None, Some(self))
-                } else if self.x.end < *ref_ {
-                    (Some(self), None)
-                } else {
-                    let mut accepted = self.clone();
-                    let mut rejected = self;
-                    accepted.x.end = ref_ - 1;
-                    rejected.x.start = *ref_;
-                    (Some(accepted), Some(rejected))
-                }
+        let axis = xmas_axis(*c);
+        let wrap = |bounds: Option<HyperBox<usize, 4>>| bounds.map(|bounds| XmasPresent { bounds });
+        match comparator {
+            Comparator::LessThan(ref_) => {
+                let (accepted, rejected) = self.bounds.split(axis, *ref_);
+                (wrap(accepted), wrap(rejected))
             }
-            ('x', Comparator::GreaterThan(ref_)) => {
-                if self.x.end <= *ref_ {
-                    (None, Some(self))
-                } else if self.x.start > *ref_ {
-                    (Some(self), None)
-                } else {
-                    let mut accepted = self.clone();
-                    let mut rejected = self;
-                    accepted.x.start = ref_ + 1;
-                    rejected.x.end = *ref_;
-                    (Some(accepted), Some(rejected))
-                }
-            }
-            ('m', Comparator::LessThan(ref_)) => {
-                if self.m.start >= *ref_ {
-                    (None, Some(self))
-                } else if self.m.end < *ref_ {
-                    (Some(self), None)
-                } else {
-                    let mut accepted = self.clone();
-                    let mut rejected = self;
-                    accepted.m.end = ref_ - 1;
-                    rejected.m.start = *ref_;
-                    (Some(accepted), Some(rejected))
-                }
+            Comparator::GreaterThan(ref_) => {
+                let (rejected, accepted) = self.bounds.split(axis, ref_ + 1);
+                (wrap(accepted), wrap(rejected))
             }
-            ('m', Comparator::GreaterThan(ref_)) => {
-                if self.m.end <= *ref_ {
-                    (None, Some(self))
-                } else if self.m.start > *ref_ {
-                    (Some(self), None)
-                } else {
-                    let mut accepted = self.clone();
-                    let mut rejected = self;
-                    accepted.m.start = ref_ + 1;
-                    rejected.m.end = *ref_;
-                    (Some(accepted), Some(rejected))
-                }
-            }
-            ('a', Comparator::LessThan(ref_)) => {
-                if self.a.start >= *ref_ {
-                    (None, Some(self))
-                } else if self.a.end < *ref_ {
-                    (Some(self), None)
-                } else {
-                    let mut accepted = self.clone();
-                    let mut rejected = self;
-                    accepted.a.end = ref_ - 1;
-                    rejected.a.start = *ref_;
-                    (Some(accepted), Some(rejected))
-                }
-            }
-            ('a', Comparator::GreaterThan(ref_)) => {
-                if self.a.end <= *ref_ {
-                    (None, Some(self))
-                } else if self.a.start > *ref_ {
-                    (Some(self), None)
-                } else {
-                    let mut accepted = self.clone();
-                    let mut rejected = self;
-                    accepted.a.start = ref_ + 1;
-                    rejected.a.end = *ref_;
-                    (Some(accepted), Some(rejected))
-                }
-            }
-            ('s', Comparator::LessThan(ref_)) => {
-                if self.s.start >= *ref_ {
-                    (None, Some(self))
-                } else if self.s.end < *ref_ {
-                    (Some(self), None)
-                } else {
-                    let mut accepted = self.clone();
-                    let mut rejected = self;
-                    accepted.s.end = ref_ - 1;
-                    rejected.s.start = *ref_;
-                    (Some(accepted), Some(rejected))
-                }
-            }
-            ('s', Comparator::GreaterThan(ref_)) => {
-                if self.s.end <= *ref_ {
-                    (None, Some(self))
-                } else if self.s.start > *ref_ {
-                    (Some(self), None)
-                } else {
-                    let mut accepted = self.clone();
-                    let mut rejected = self;
-                    accepted.s.start = ref_ + 1;
-                    rejected.s.end = *ref_;
-                    (Some(accepted), Some(rejected))
-                }
-            }
-            _ => panic!("oh no!"),
         }
     }
 }
 
+/// The axis of an `XmasPresent` (and the index into an `Xmas`'s ratings)
+/// that a category character refers to
+fn xmas_axis(c: char) -> usize {
+    match c {
+        'x' => 0,
+        'm' => 1,
+        'a' => 2,
+        's' => 3,
+        _ => panic!("oh no!"),
+    }
+}
+
 fn parse_input(mut reader: AocBufReader) -> (HashMap<String, Workflow>, Vec<Xmas>) {
     let mut workflows: HashMap<String, Workflow> = HashMap::new();
     loop {
@@ -347,7 +260,7 @@ fn parse_input(mut reader: AocBufReader) -> (HashMap<String, Workflow>, Vec<Xmas
     }
 
     let mut xmases: Vec<Xmas> = Vec::new();
-    while let Some(line) = reader.next() {
+    for line in reader {
         xmases.push(parse_xmas(line));
     }
     (workflows, xmases)
@@ -357,10 +270,10 @@ fn parse_workflow(s: String) -> Workflow {
     let capture = WORKFLOW_RE.captures(&s).unwrap();
     let id = capture["id"].to_owned();
     let dest = capture["dest"].to_owned();
-    let opers = capture["opers"].to_owned();
-
-    let mut operations: Vec<(Operation, String)> =
-        opers.split(",").into_iter().map(parse_operation).collect();
+    let mut operations: Vec<(Operation, String)> = match capture.name("opers") {
+        Some(opers) => opers.as_str().split(",").map(parse_operation).collect(),
+        None => Vec::new(),
+    };
     operations.push((Operation::Nullary, dest));
     Workflow { id, operations }
 }
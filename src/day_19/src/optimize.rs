@@ -0,0 +1,218 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use crate::query::unreachable_rules;
+use crate::{Comparator, Operation, Workflow, XmasPresent};
+
+/// Simplify `workflows` without changing where any part ends up:
+///  - drop rules that no part can take
+///  - drop trailing rules that send parts to the same place as the fallback
+///  - inline workflows that always route to a single target
+///  - drop workflows that can't be reached from "in"
+///
+/// Each pass can enable the others, so they are repeated until nothing changes.
+/// Ratings are assumed to be in 1..=4000, as in the puzzle: a rule like
+/// `x<1:R` is dropped as dead, so a part rated outside that range may end up
+/// somewhere else. Panics if the workflows loop.
+pub fn optimize(workflows: &HashMap<String, Workflow>) -> HashMap<String, Workflow> {
+    let mut result = workflows.clone();
+    loop {
+        let mut changed = drop_dead_rules(&mut result);
+        changed |= merge_redundant_rules(&mut result);
+        changed |= inline_single_target_workflows(&mut result);
+        changed |= drop_unreachable_workflows(&mut result);
+        if !changed {
+            return result;
+        }
+    }
+}
+
+/// The workflows in the puzzle's `name{...}` syntax, one per line, "in" first
+pub fn to_puzzle_syntax(workflows: &HashMap<String, Workflow>) -> String {
+    let mut ids: Vec<&String> = workflows.keys().collect();
+    ids.sort_by_key(|id| (id.as_str() != "in", id.as_str()));
+    ids.into_iter()
+        .map(|id| format!("{}\n", workflows[id]))
+        .collect()
+}
+
+/// Only conditional rules are dropped; "dead" is judged over ratings in
+/// 1..=4000
+fn drop_dead_rules(workflows: &mut HashMap<String, Workflow>) -> bool {
+    let mut changed = false;
+    // descending, so removing a rule doesn't shift the indices still to come
+    for (workflow_id, rule_idx) in unreachable_rules(XmasPresent::universal(), workflows)
+        .into_iter()
+        .rev()
+    {
+        let workflow = workflows.get_mut(&workflow_id).unwrap();
+        if let Operation::Unary(..) = workflow.operations[rule_idx].0 {
+            workflow.operations.remove(rule_idx);
+            changed = true;
+        }
+    }
+    changed
+}
+
+/// `...,x<10:R,R` becomes `...,R`: a trailing rule that goes where the
+/// fallback goes anyway changes nothing, whatever the ratings
+fn merge_redundant_rules(workflows: &mut HashMap<String, Workflow>) -> bool {
+    let mut changed = false;
+    for workflow in workflows.values_mut() {
+        while let [.., (_, second_to_last), (_, last)] = &workflow.operations[..] {
+            if second_to_last != last {
+                break;
+            }
+            let n_operations = workflow.operations.len();
+            workflow.operations.remove(n_operations - 2);
+            changed = true;
+        }
+    }
+    changed
+}
+
+fn inline_single_target_workflows(workflows: &mut HashMap<String, Workflow>) -> bool {
+    let single_targets: HashMap<String, String> = workflows
+        .values()
+        .filter(|workflow| workflow.id != "in")
+        .filter_map(|workflow| match &workflow.operations[..] {
+            [(Operation::Nullary, dest)] if *dest != workflow.id => {
+                Some((workflow.id.clone(), dest.clone()))
+            }
+            _ => None,
+        })
+        .collect();
+
+    let resolve = |dest: &String| -> String {
+        let mut dest = dest;
+        let mut seen: HashSet<&String> = HashSet::new();
+        while let Some(next) = single_targets.get(dest) {
+            if !seen.insert(dest) {
+                panic!("workflow {} is part of a cycle", dest);
+            }
+            dest = next;
+        }
+        dest.clone()
+    };
+
+    let mut changed = false;
+    for workflow in workflows.values_mut() {
+        for (_, dest) in workflow.operations.iter_mut() {
+            let resolved = resolve(dest);
+            if resolved != *dest {
+                *dest = resolved;
+                changed = true;
+            }
+        }
+    }
+    changed
+}
+
+fn drop_unreachable_workflows(workflows: &mut HashMap<String, Workflow>) -> bool {
+    let mut reachable: HashSet<String> = HashSet::new();
+    let mut to_visit: Vec<String> = vec!["in".to_string()];
+    while let Some(workflow_id) = to_visit.pop() {
+        if let Some(workflow) = workflows.get(&workflow_id) {
+            if reachable.insert(workflow_id) {
+                to_visit.extend(workflow.operations.iter().map(|(_, dest)| dest.clone()));
+            }
+        }
+    }
+
+    let n_workflows = workflows.len();
+    workflows.retain(|workflow_id, _| reachable.contains(workflow_id));
+    workflows.len() != n_workflows
+}
+
+impl fmt::Display for Workflow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let rules: Vec<String> = self
+            .operations
+            .iter()
+            .map(|(operation, dest)| match operation {
+                Operation::Nullary => dest.clone(),
+                Operation::Unary(c, Comparator::LessThan(reference)) => {
+                    format!("{c}<{reference}:{dest}")
+                }
+                Operation::Unary(c, Comparator::GreaterThan(reference)) => {
+                    format!("{c}>{reference}:{dest}")
+                }
+            })
+            .collect();
+        write!(f, "{}{{{}}}", self.id, rules.join(","))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::query;
+    use crate::{parse_input, parse_workflow, xmas_accepted_part_1};
+    use shared::input::AocBufReader;
+
+    fn workflows_from(lines: &[&str]) -> HashMap<String, Workflow> {
+        lines
+            .iter()
+            .map(|s| parse_workflow(s.to_string()))
+            .map(|w| (w.id.clone(), w))
+            .collect()
+    }
+
+    #[test]
+    fn test_optimize_preserves_part_2() {
+        let (workflows, xmases) = parse_input(AocBufReader::from_string("inputs/test.txt"));
+        let optimized = optimize(&workflows);
+
+        assert!(optimized.len() < workflows.len());
+        assert_eq!(
+            query(XmasPresent::universal(), &optimized).n_accepted(),
+            query(XmasPresent::universal(), &workflows).n_accepted()
+        );
+        for xmas in xmases.iter() {
+            assert_eq!(
+                xmas_accepted_part_1(xmas, &optimized),
+                xmas_accepted_part_1(xmas, &workflows)
+            );
+        }
+    }
+
+    #[test]
+    fn test_optimize() {
+        let workflows = workflows_from(&[
+            "in{s<1351:px,x<10:lnx,x<5:R,qqz}",
+            "px{a<2006:A,A}",
+            "lnx{gd}",
+            "gd{a>3333:R,R}",
+            "qqz{m<1801:px,R}",
+            "orphan{x>1:A,R}",
+        ]);
+
+        assert_eq!(
+            to_puzzle_syntax(&optimize(&workflows)),
+            "in{s<1351:A,x<10:R,qqz}\nqqz{m<1801:A,R}\n"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "workflow in is part of a cycle")]
+    fn test_optimize_cycle_through_rules() {
+        optimize(&workflows_from(&["in{x<10:a,A}", "a{x<5:in,R}"]));
+    }
+
+    #[test]
+    #[should_panic(expected = "is part of a cycle")]
+    fn test_optimize_cycle_of_fallbacks() {
+        // never visited from "in", so only inlining can trip over it
+        optimize(&workflows_from(&["in{x<10:A,R}", "b{c}", "c{b}"]));
+    }
+
+    #[test]
+    fn test_to_puzzle_syntax_round_trip() {
+        let (workflows, _) = parse_input(AocBufReader::from_string("inputs/test.txt"));
+        let syntax = to_puzzle_syntax(&workflows);
+
+        assert!(syntax.starts_with("in{s<1351:px,qqz}\n"));
+        let reparsed = workflows_from(&syntax.lines().collect::<Vec<&str>>());
+        assert_eq!(to_puzzle_syntax(&reparsed), syntax);
+    }
+}
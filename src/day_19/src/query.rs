@@ -0,0 +1,209 @@
+use std::collections::{HashMap, HashSet};
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::{Comparator, Workflow, XmasPresent};
+
+static CONSTRAINT_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(?<input>[xmas])(?<comparator>[<>])(?<reference>[0-9]+)$").unwrap());
+
+/// A disjoint box of parts that all take the same route through the workflows
+#[derive(Debug, Clone)]
+pub struct Route {
+    pub present: XmasPresent,
+    /// `(workflow id, index of the rule taken)` for every workflow visited,
+    /// starting at "in"
+    pub path: Vec<(String, usize)>,
+}
+
+/// Every part of the queried box, sorted by where it ends up
+#[derive(Debug, Default)]
+pub struct QueryResult {
+    pub accepted: Vec<Route>,
+    pub rejected: Vec<Route>,
+}
+
+impl QueryResult {
+    pub fn n_accepted(&self) -> usize {
+        self.accepted.iter().map(|r| r.present.n_xmases()).sum()
+    }
+
+    pub fn n_rejected(&self) -> usize {
+        self.rejected.iter().map(|r| r.present.n_xmases()).sum()
+    }
+}
+
+impl XmasPresent {
+    /// The universal present narrowed by constraints like `x<1000, s>2000`.
+    /// None if the constraints leave nothing.
+    pub fn from_constraints(s: &str) -> Option<Self> {
+        let mut result = XmasPresent::universal();
+        for constraint in s.split(',').map(|c| c.trim()).filter(|c| !c.is_empty()) {
+            let capture = CONSTRAINT_RE
+                .captures(constraint)
+                .unwrap_or_else(|| panic!("Failed to parse constraint: {}", constraint));
+            let reference = capture["reference"].parse::<usize>().unwrap();
+            let comparator = match &capture["comparator"] {
+                "<" => Comparator::LessThan(reference),
+                _ => Comparator::GreaterThan(reference),
+            };
+            let input_char = capture["input"].chars().next().unwrap();
+
+            result = result.split(&input_char, &comparator).0?;
+        }
+        Some(result)
+    }
+}
+
+/// Push `xmas_present` through the workflows starting at "in", recording the
+/// route every piece of it takes. Panics if the workflows loop.
+pub fn query(xmas_present: XmasPresent, workflows: &HashMap<String, Workflow>) -> QueryResult {
+    let mut result = QueryResult::default();
+    let mut path: Vec<(String, usize)> = Vec::new();
+    walk(xmas_present, "in", workflows, &mut path, &mut result);
+    result
+}
+
+fn walk(
+    xmas_present: XmasPresent,
+    workflow_id: &str,
+    workflows: &HashMap<String, Workflow>,
+    path: &mut Vec<(String, usize)>,
+    result: &mut QueryResult,
+) {
+    if workflow_id == "A" || workflow_id == "R" {
+        let route = Route {
+            present: xmas_present,
+            path: path.clone(),
+        };
+        if workflow_id == "A" {
+            result.accepted.push(route);
+        } else {
+            result.rejected.push(route);
+        }
+        return;
+    }
+
+    if path.iter().any(|(visited, _)| visited == workflow_id) {
+        panic!("workflow {} is part of a cycle", workflow_id);
+    }
+
+    let workflow = workflows.get(workflow_id).unwrap();
+    for (present, rule_idx) in workflow.split_by_rule(xmas_present) {
+        path.push((workflow_id.to_string(), rule_idx));
+        walk(
+            present,
+            &workflow.operations[rule_idx].1,
+            workflows,
+            path,
+            result,
+        );
+        path.pop();
+    }
+}
+
+/// `(workflow id, rule index)` of every rule that no part of `xmas_present`
+/// ever takes, either because its workflow is never visited or because the
+/// earlier rules of its workflow already claim everything that could match it
+pub fn unreachable_rules(
+    xmas_present: XmasPresent,
+    workflows: &HashMap<String, Workflow>,
+) -> Vec<(String, usize)> {
+    let result = query(xmas_present, workflows);
+    let taken: HashSet<(String, usize)> = result
+        .accepted
+        .iter()
+        .chain(result.rejected.iter())
+        .flat_map(|route| route.path.iter().cloned())
+        .collect();
+
+    let mut unreachable: Vec<(String, usize)> = workflows
+        .values()
+        .flat_map(|workflow| {
+            (0..workflow.operations.len()).map(|rule_idx| (workflow.id.clone(), rule_idx))
+        })
+        .filter(|rule| !taken.contains(rule))
+        .collect();
+    unreachable.sort();
+    unreachable
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_input, parse_workflow};
+    use shared::input::AocBufReader;
+
+    #[test]
+    fn test_query_universal() {
+        let (workflows, _) = parse_input(AocBufReader::from_string("inputs/test.txt"));
+        let result = query(XmasPresent::universal(), &workflows);
+
+        assert_eq!(result.n_accepted(), 167409079868000);
+        assert_eq!(result.n_accepted() + result.n_rejected(), 4000usize.pow(4));
+        assert!(result
+            .accepted
+            .iter()
+            .chain(result.rejected.iter())
+            .all(|route| route.path[0].0 == "in"));
+    }
+
+    #[test]
+    fn test_query_constrained() {
+        let (workflows, _) = parse_input(AocBufReader::from_string("inputs/test.txt"));
+        let present = XmasPresent::from_constraints("x<1000, s>2000").unwrap();
+        assert_eq!(present.n_xmases(), 999 * 4000 * 4000 * 2000);
+
+        let result = query(present, &workflows);
+        assert_eq!(
+            result.n_accepted() + result.n_rejected(),
+            999 * 4000 * 4000 * 2000
+        );
+
+        // s > 2000 always takes the second rule of "in", to qqz
+        assert!(result.accepted.iter().all(|route| route.path[..2]
+            == [("in".to_string(), 1), ("qqz".to_string(), route.path[1].1)]));
+
+        assert!(XmasPresent::from_constraints("x<1000, x>2000").is_none());
+    }
+
+    #[test]
+    fn test_query_path() {
+        let workflows: HashMap<String, Workflow> = ["in{x>10:two,R}", "two{m<5:A,R}"]
+            .into_iter()
+            .map(|s| parse_workflow(s.to_string()))
+            .map(|w| (w.id.clone(), w))
+            .collect();
+
+        let result = query(XmasPresent::universal(), &workflows);
+        assert_eq!(result.accepted.len(), 1);
+        assert_eq!(
+            result.accepted[0].path,
+            vec![("in".to_string(), 0), ("two".to_string(), 0)]
+        );
+        assert_eq!(result.n_accepted(), 3990 * 4 * 4000 * 4000);
+    }
+
+    #[test]
+    fn test_unreachable_rules() {
+        let workflows: HashMap<String, Workflow> =
+            ["in{x>10:two,x>20:R,A}", "two{m<5:A,R}", "orphan{a<5:A,R}"]
+                .into_iter()
+                .map(|s| parse_workflow(s.to_string()))
+                .map(|w| (w.id.clone(), w))
+                .collect();
+
+        assert_eq!(
+            unreachable_rules(XmasPresent::universal(), &workflows),
+            vec![
+                ("in".to_string(), 1),
+                ("orphan".to_string(), 0),
+                ("orphan".to_string(), 1)
+            ]
+        );
+
+        let present = XmasPresent::from_constraints("m>100").unwrap();
+        assert!(unreachable_rules(present, &workflows).contains(&("two".to_string(), 0)));
+    }
+}
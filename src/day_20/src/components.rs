@@ -0,0 +1,292 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::PulseType;
+
+/// The behaviour of one kind of module. The board takes care of routing:
+/// a component only decides which pulse (if any) to send to all of its
+/// module's destinations when a pulse arrives.
+pub trait Component: fmt::Debug + std::any::Any {
+    /// A short description of the component, e.g. "flip-flop"
+    fn kind(&self) -> &'static str;
+
+    /// Called during start up, once for every module that sends to this one
+    fn connect_input(&mut self, _src: &str) {}
+
+    fn process_pulse(&mut self, pulse_type: PulseType, src: &str) -> Option<PulseType>;
+
+    /// The component's memory in a canonical form: two components of the
+    /// same kind are in the same state iff their `state`s are equal
+    fn state(&self) -> Vec<u8>;
+
+    fn box_clone(&self) -> Box<dyn Component>;
+}
+
+impl Clone for Box<dyn Component> {
+    fn clone(&self) -> Self {
+        self.box_clone()
+    }
+}
+
+/// Passes every pulse on unchanged
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Broadcaster;
+
+impl Component for Broadcaster {
+    fn kind(&self) -> &'static str {
+        "broadcaster"
+    }
+
+    fn process_pulse(&mut self, pulse_type: PulseType, _src: &str) -> Option<PulseType> {
+        Some(pulse_type)
+    }
+
+    fn state(&self) -> Vec<u8> {
+        Vec::new()
+    }
+
+    fn box_clone(&self) -> Box<dyn Component> {
+        Box::new(self.clone())
+    }
+}
+
+/// Ignores high pulses and toggles on every low pulse, sending high when it
+/// turns on and low when it turns off
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct FlipFlop {
+    pub is_on: bool,
+}
+
+impl Component for FlipFlop {
+    fn kind(&self) -> &'static str {
+        "flip-flop"
+    }
+
+    fn process_pulse(&mut self, pulse_type: PulseType, _src: &str) -> Option<PulseType> {
+        if pulse_type == PulseType::High {
+            return None;
+        }
+
+        let pt = if self.is_on {
+            PulseType::Low
+        } else {
+            PulseType::High
+        };
+        self.is_on = !self.is_on;
+        Some(pt)
+    }
+
+    fn state(&self) -> Vec<u8> {
+        vec![u8::from(self.is_on)]
+    }
+
+    fn box_clone(&self) -> Box<dyn Component> {
+        Box::new(self.clone())
+    }
+}
+
+/// Remembers the last pulse from each input and sends low only when all of
+/// them were high
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Conjunction {
+    pub memory: HashMap<String, PulseType>,
+}
+
+impl Component for Conjunction {
+    fn kind(&self) -> &'static str {
+        "conjunction"
+    }
+
+    fn connect_input(&mut self, src: &str) {
+        self.memory.insert(src.to_string(), PulseType::Low);
+    }
+
+    fn process_pulse(&mut self, pulse_type: PulseType, src: &str) -> Option<PulseType> {
+        *self.memory.get_mut(src).unwrap() = pulse_type;
+        if self.memory.values().all(|x| *x == PulseType::High) {
+            Some(PulseType::Low)
+        } else {
+            Some(PulseType::High)
+        }
+    }
+
+    /// One byte per input, ordered by input name
+    fn state(&self) -> Vec<u8> {
+        let mut memory: Vec<(&String, &PulseType)> = self.memory.iter().collect();
+        memory.sort_by_key(|(name, _)| *name);
+        memory
+            .into_iter()
+            .map(|(_, pt)| u8::from(*pt == PulseType::High))
+            .collect()
+    }
+
+    fn box_clone(&self) -> Box<dyn Component> {
+        Box::new(self.clone())
+    }
+}
+
+type ComponentFactory = Box<dyn Fn() -> Box<dyn Component>>;
+
+/// Maps the prefix character in front of a module name to the component
+/// it builds. The default registry knows the puzzle's `%` and `&`, and
+/// `!`, `~`, `#` (counting to 2) and `@` for the components below, which the puzzle
+/// doesn't use.
+pub struct ComponentRegistry {
+    factories: HashMap<char, ComponentFactory>,
+}
+
+impl Default for ComponentRegistry {
+    fn default() -> Self {
+        let mut registry = Self {
+            factories: HashMap::new(),
+        };
+        registry.register('%', || Box::new(FlipFlop::default()));
+        registry.register('&', || Box::new(Conjunction::default()));
+        registry.register('!', || Box::new(Inverter));
+        registry.register('~', || Box::new(Delay::default()));
+        registry.register('#', || Box::new(Counter::new(2)));
+        registry.register('@', || Box::new(OutputSink::default()));
+        registry
+    }
+}
+
+impl ComponentRegistry {
+    /// Replaces any component already registered under `prefix`
+    pub fn register<F>(&mut self, prefix: char, factory: F)
+    where
+        F: Fn() -> Box<dyn Component> + 'static,
+    {
+        self.factories.insert(prefix, Box::new(factory));
+    }
+
+    pub fn build(&self, prefix: char) -> Option<Box<dyn Component>> {
+        self.factories.get(&prefix).map(|factory| factory())
+    }
+}
+
+/// Sends the opposite of every pulse it receives
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Inverter;
+
+impl Component for Inverter {
+    fn kind(&self) -> &'static str {
+        "inverter"
+    }
+
+    fn process_pulse(&mut self, pulse_type: PulseType, _src: &str) -> Option<PulseType> {
+        match pulse_type {
+            PulseType::Low => Some(PulseType::High),
+            PulseType::High => Some(PulseType::Low),
+        }
+    }
+
+    fn state(&self) -> Vec<u8> {
+        Vec::new()
+    }
+
+    fn box_clone(&self) -> Box<dyn Component> {
+        Box::new(self.clone())
+    }
+}
+
+/// A one-pulse delay line: every pulse received pushes out the one received
+/// before it (starting from a low pulse)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Delay {
+    pub held: PulseType,
+}
+
+impl Default for Delay {
+    fn default() -> Self {
+        Self {
+            held: PulseType::Low,
+        }
+    }
+}
+
+impl Component for Delay {
+    fn kind(&self) -> &'static str {
+        "delay"
+    }
+
+    fn process_pulse(&mut self, pulse_type: PulseType, _src: &str) -> Option<PulseType> {
+        Some(std::mem::replace(&mut self.held, pulse_type))
+    }
+
+    fn state(&self) -> Vec<u8> {
+        vec![u8::from(self.held == PulseType::High)]
+    }
+
+    fn box_clone(&self) -> Box<dyn Component> {
+        Box::new(self.clone())
+    }
+}
+
+/// Sends a high pulse on every `modulus`th pulse it receives, and nothing
+/// otherwise
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Counter {
+    pub modulus: usize,
+    pub count: usize,
+}
+
+impl Counter {
+    pub fn new(modulus: usize) -> Self {
+        assert!(modulus > 0, "a counter's modulus must be at least 1");
+        Self { modulus, count: 0 }
+    }
+}
+
+impl Component for Counter {
+    fn kind(&self) -> &'static str {
+        "counter"
+    }
+
+    fn process_pulse(&mut self, _pulse_type: PulseType, _src: &str) -> Option<PulseType> {
+        self.count = (self.count + 1) % self.modulus;
+        if self.count == 0 {
+            Some(PulseType::High)
+        } else {
+            None
+        }
+    }
+
+    fn state(&self) -> Vec<u8> {
+        self.count.to_le_bytes().to_vec()
+    }
+
+    fn box_clone(&self) -> Box<dyn Component> {
+        Box::new(self.clone())
+    }
+}
+
+/// Swallows every pulse, keeping a tally of what arrived
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct OutputSink {
+    pub n_low_received: usize,
+    pub n_high_received: usize,
+}
+
+impl Component for OutputSink {
+    fn kind(&self) -> &'static str {
+        "output"
+    }
+
+    fn process_pulse(&mut self, pulse_type: PulseType, _src: &str) -> Option<PulseType> {
+        match pulse_type {
+            PulseType::Low => self.n_low_received += 1,
+            PulseType::High => self.n_high_received += 1,
+        }
+        None
+    }
+
+    fn state(&self) -> Vec<u8> {
+        let mut state = self.n_low_received.to_le_bytes().to_vec();
+        state.extend(self.n_high_received.to_le_bytes());
+        state
+    }
+
+    fn box_clone(&self) -> Box<dyn Component> {
+        Box::new(self.clone())
+    }
+}
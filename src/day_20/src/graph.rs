@@ -0,0 +1,81 @@
+use std::collections::HashSet;
+
+use shared::dot::Graph;
+
+use crate::ModuleBoard;
+
+impl ModuleBoard {
+    /// Modules as nodes, drawn by component kind. The conjunctions that
+    /// feed "rx" through nothing but conjunctions, the chain that part 2
+    /// reasons about, are highlighted.
+    pub fn to_graph(&self) -> Graph {
+        let mut names: Vec<&String> = self.modules.keys().collect();
+        names.sort_by_key(|name| (name.as_str() != "broadcaster", name.as_str()));
+
+        let mut graph = Graph::directed();
+        for name in names.iter() {
+            graph.add_node(name, Some(self.modules[*name].component.kind()));
+        }
+
+        let mut highlighted: HashSet<&str> = HashSet::from(["rx"]);
+        let mut frontier: Vec<&str> = vec!["rx"];
+        while let Some(receiver) = frontier.pop() {
+            for name in names.iter() {
+                let module = &self.modules[*name];
+                if module.component.kind() == "conjunction"
+                    && module.destinations.iter().any(|dest| dest == receiver)
+                    && highlighted.insert(name.as_str())
+                {
+                    frontier.push(name);
+                }
+            }
+        }
+
+        for name in names {
+            for dest in self.modules[name].destinations.iter() {
+                let edge_idx = graph.add_edge(name, dest, None);
+                if highlighted.contains(name.as_str()) && highlighted.contains(dest.as_str()) {
+                    graph.highlight_edge(edge_idx);
+                }
+            }
+        }
+
+        graph
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{module_start_up, ModuleBoard};
+    use shared::input::AocBufReader;
+
+    #[test]
+    fn test_to_graph() {
+        let modules = module_start_up(AocBufReader::from_string("inputs/test_2.txt"));
+        let graph = ModuleBoard::new(modules).to_graph();
+
+        assert_eq!(graph.nodes()[0].id, "broadcaster");
+        let ca = graph.nodes().iter().find(|node| node.id == "ca").unwrap();
+        assert_eq!(ca.kind.as_deref(), Some("conjunction"));
+        let a0 = graph.nodes().iter().find(|node| node.id == "a0").unwrap();
+        assert_eq!(a0.kind.as_deref(), Some("flip-flop"));
+
+        let mut highlighted: Vec<(&str, &str)> = graph
+            .edges()
+            .iter()
+            .filter(|edge| edge.highlighted)
+            .map(|edge| (edge.from.as_str(), edge.to.as_str()))
+            .collect();
+        highlighted.sort();
+        assert_eq!(
+            highlighted,
+            vec![
+                ("ca", "ia"),
+                ("cb", "ib"),
+                ("ia", "out"),
+                ("ib", "out"),
+                ("out", "rx")
+            ]
+        );
+    }
+}
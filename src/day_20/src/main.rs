@@ -1,16 +1,38 @@
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::collections::{HashMap, VecDeque};
 use std::fmt;
 
 use num::integer;
 use once_cell::sync::Lazy;
 use regex::Regex;
 
+use shared::dot::Format;
 use shared::input::AocBufReader;
 
+mod components;
+mod graph;
+mod period;
+mod trace;
+
+use components::{Broadcaster, Component, ComponentRegistry};
+use trace::{Trace, TraceEvent};
+
 static INPUT_RE: Lazy<Regex> =
     Lazy::new(|| Regex::new(r"^(?<name>.*) -> (?<destinations>.*)$").unwrap());
 
 fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(|arg| arg.as_str()) {
+        Some("--trace") => return print_trace(&args[1..]),
+        Some("--replay") => return print_replay(&args[1..]),
+        _ => (),
+    }
+
+    if let Some(format) = Format::from_args() {
+        let modules = module_start_up(AocBufReader::from_string("inputs/part_1.txt"));
+        print!("{}", ModuleBoard::new(modules).to_graph().render(format));
+        return;
+    }
+
     let result = part_1(AocBufReader::from_string("inputs/part_1.txt"));
     println!("part 1: {result}");
 
@@ -18,6 +40,72 @@ fn main() {
     println!("part 2: {result}");
 }
 
+/// `--trace <presses> [--module <name>] [--low|--high] [--csv]`: every
+/// pulse sent during the first `presses` presses, optionally filtered
+fn print_trace(args: &[String]) {
+    let n_presses = args[0].parse::<usize>().unwrap();
+    let modules = module_start_up(AocBufReader::from_string("inputs/part_1.txt"));
+    let mut module_board = ModuleBoard::new(modules);
+    module_board.enable_trace();
+    for _ in 0..n_presses {
+        module_board.push_button();
+    }
+    let mut trace = module_board.trace.unwrap();
+
+    let flag_value = |flag: &str| {
+        args.iter()
+            .position(|arg| arg == flag)
+            .map(|idx| args[idx + 1].as_str())
+    };
+    if let Some(module) = flag_value("--module") {
+        let mut filtered = Trace::default();
+        for event in trace.filter_module(module) {
+            filtered.record(event.clone());
+        }
+        trace = filtered;
+    }
+    for (flag, pulse_type) in [("--low", PulseType::Low), ("--high", PulseType::High)] {
+        if args.iter().any(|arg| arg == flag) {
+            let mut filtered = Trace::default();
+            for event in trace.filter_pulse_type(&pulse_type) {
+                filtered.record(event.clone());
+            }
+            trace = filtered;
+        }
+    }
+
+    match args.iter().any(|arg| arg == "--csv") {
+        true => print!("{}", trace.to_csv()),
+        false => print!("{}", trace.to_text()),
+    }
+}
+
+/// `--replay <trace file> <press>`: the state of every module right after
+/// `press`, rebuilt from a trace written by `--trace`
+fn print_replay(args: &[String]) {
+    let trace = Trace::from_text(&std::fs::read_to_string(&args[0]).unwrap());
+    let through_press = args[1].parse::<usize>().unwrap();
+    let modules = module_start_up(AocBufReader::from_string("inputs/part_1.txt"));
+    let module_board = match ModuleBoard::replay(modules, &trace, through_press) {
+        Ok(module_board) => module_board,
+        Err(err) => panic!("Can't replay {}: {err}", args[0]),
+    };
+
+    println!(
+        "after press {}: {} low and {} high pulses, {} traced in all, state hash {:016x}",
+        module_board.n_presses,
+        module_board.n_low_pulses_sent,
+        module_board.n_high_pulses_sent,
+        trace.events().len(),
+        module_board.state_hash()
+    );
+    let mut names: Vec<&String> = module_board.modules.keys().collect();
+    names.sort();
+    for name in names {
+        println!("{name}: {:?}", module_board.modules[name].component);
+    }
+}
+
 fn part_1(reader: AocBufReader) -> usize {
     let modules = module_start_up(reader);
     let mut module_board = ModuleBoard::new(modules);
@@ -50,61 +138,59 @@ fn part_1(reader: AocBufReader) -> usize {
 /// and each third layer conjunction module is fed by 8 or more modules
 /// meaning &dc will only emit a high pulse when &dj emits a high pulse,
 /// and &dj will only emit a high pulse when all of its most recent input pulses
-/// are "synced" (all high). There are a finite number of modules and hence
-/// there are a finite number states for the collection of inputs to our
-/// conjunction layer modules and with infinite prodding they must eventually reveal a cycle.
-/// The cycles for each of the inputs to &ns only line up at their least common multiple.
+/// are "synced" (all high).
+///
+/// Each &dj together with its flip-flops is a strongly connected subcircuit
+/// that only hears from the broadcaster, so once it is back in its start up
+/// state it must repeat itself exactly. `subcircuit_periods` finds the first
+/// press at which that happens for each of them, and the cycles for each of
+/// the inputs to &ns only line up at their least common multiple. Every
+/// subcircuit has to have a period, or the answer would be wrong, and only
+/// the ones that feed &ns count.
 fn part_2(reader: AocBufReader) -> usize {
     let modules = module_start_up(reader);
-    let mut module_board = ModuleBoard::new(modules);
-    let mut dc_emits_high: Vec<usize> = Vec::new();
-    let mut rv_emits_high: Vec<usize> = Vec::new();
-    let mut vp_emits_high: Vec<usize> = Vec::new();
-    let mut cq_emits_high: Vec<usize> = Vec::new();
-
-    for nth_press in 1..100_000 {
-        module_board.push_button();
-        if module_board.dc_sent_high {
-            dc_emits_high.push(nth_press);
-        }
-        if module_board.rv_sent_high {
-            rv_emits_high.push(nth_press);
-        }
-        if module_board.vp_sent_high {
-            vp_emits_high.push(nth_press);
-        }
-        if module_board.cq_sent_high {
-            cq_emits_high.push(nth_press);
-        }
-    }
-
-    let cycle_lengths = [
-        assert_cycle(dc_emits_high),
-        assert_cycle(rv_emits_high),
-        assert_cycle(vp_emits_high),
-        assert_cycle(cq_emits_high),
-    ];
-    cycle_lengths
+    let module_board = ModuleBoard::new(modules);
+    let max_presses = 100_000;
+
+    let rx_conjunction = module_board
+        .conjunction_before("rx")
+        .expect("rx should be fed by a single conjunction");
+    let periods = module_board
+        .subcircuit_periods(max_presses)
+        .unwrap_or_else(|missing| {
+            panic!(
+                "{} subcircuit(s) didn't repeat within {max_presses} presses, starting with {:?}",
+                missing.len(),
+                missing[0].modules
+            )
+        });
+
+    let rx_periods: Vec<usize> = periods
         .into_iter()
-        .fold(1usize, |acc, next| integer::lcm(acc, next))
-}
-
-fn assert_cycle(periodic_signal: Vec<usize>) -> usize {
-    let diffs: HashSet<usize> = periodic_signal[0..]
-        .iter()
-        .zip(periodic_signal[1..].iter())
-        .map(|(and, then)| then - and)
+        .filter(|(subcircuit, _)| module_board.reaches(&subcircuit.feeds, rx_conjunction))
+        .map(|(_, period)| period)
         .collect();
-
-    assert!(diffs.len() == 1);
-    diffs.into_iter().next().unwrap()
+    assert!(
+        !rx_periods.is_empty(),
+        "no subcircuit feeds {rx_conjunction}"
+    );
+    rx_periods.into_iter().fold(1usize, integer::lcm)
 }
 
 fn module_start_up(reader: AocBufReader) -> HashMap<String, Module> {
+    module_start_up_with(reader, &ComponentRegistry::default())
+}
+
+/// Parse the modules, resolving prefixes with `registry`, and tell every
+/// module which modules send to it
+fn module_start_up_with(
+    reader: AocBufReader,
+    registry: &ComponentRegistry,
+) -> HashMap<String, Module> {
     let mut modules: HashMap<String, Module> = reader
         .into_iter()
         .map(|line| {
-            let module = parse_module(line);
+            let module = parse_module(line, registry);
             (module.name.clone(), module)
         })
         .collect();
@@ -113,16 +199,12 @@ fn module_start_up(reader: AocBufReader) -> HashMap<String, Module> {
     for from_name in module_names {
         let to_names: Vec<String> = {
             let from = modules.get(&from_name).unwrap();
-            from.destinations.iter().cloned().collect()
+            from.destinations.to_vec()
         };
 
         for receiver_name in to_names {
-            if let Some(mut receiver) = modules.remove(&receiver_name) {
-                if let ModuleType::Conjunction(mut memory) = receiver.type_ {
-                    memory.insert(from_name.clone(), PulseType::Low);
-                    receiver.type_ = ModuleType::Conjunction(memory);
-                }
-                modules.insert(receiver_name, receiver);
+            if let Some(receiver) = modules.get_mut(&receiver_name) {
+                receiver.component.connect_input(&from_name);
             } else {
                 assert_eq!(receiver_name, "rx");
             }
@@ -135,35 +217,35 @@ fn module_start_up(reader: AocBufReader) -> HashMap<String, Module> {
 struct ModuleBoard {
     modules: HashMap<String, Module>,
     pulse_queue: VecDeque<(PulseType, String, String)>,
+    n_presses: usize,
+    n_pulses_this_press: usize,
+    trace: Option<Trace>,
     n_low_pulses_sent: usize,
     n_high_pulses_sent: usize,
-    dc_sent_high: bool,
-    rv_sent_high: bool,
-    vp_sent_high: bool,
-    cq_sent_high: bool,
 }
 
 impl ModuleBoard {
     fn new(modules: HashMap<String, Module>) -> Self {
         Self {
-            modules: modules,
+            modules,
             pulse_queue: VecDeque::new(),
+            n_presses: 0,
+            n_pulses_this_press: 0,
+            trace: None,
             n_low_pulses_sent: 0,
             n_high_pulses_sent: 0,
-            dc_sent_high: false,
-            rv_sent_high: false,
-            vp_sent_high: false,
-            cq_sent_high: false,
         }
     }
 
+    /// Record every pulse sent from now on, see `Trace`
+    fn enable_trace(&mut self) {
+        self.trace.get_or_insert_with(Trace::default);
+    }
+
     fn push_button(&mut self) {
         assert!(self.pulse_queue.is_empty());
-
-        self.dc_sent_high = false;
-        self.rv_sent_high = false;
-        self.vp_sent_high = false;
-        self.cq_sent_high = false;
+        self.n_presses += 1;
+        self.n_pulses_this_press = 0;
 
         self.pulse_queue.push_back((
             PulseType::Low,
@@ -171,7 +253,7 @@ impl ModuleBoard {
             "broadcaster".to_string(),
         ));
 
-        while self.pulse_queue.len() > 0 {
+        while !self.pulse_queue.is_empty() {
             let (pulse_type, src, destination) = self.pop_pulse();
             if let Some(module) = self.modules.get_mut(&destination) {
                 let sent_pulses = module.process_pulse(pulse_type, src);
@@ -189,25 +271,23 @@ impl ModuleBoard {
             PulseType::High => self.n_high_pulses_sent += 1,
         }
 
-        if s == "dc" && pt == PulseType::High {
-            self.dc_sent_high = true;
-        }
-        if s == "rv" && pt == PulseType::High {
-            self.rv_sent_high = true;
-        }
-        if s == "vp" && pt == PulseType::High {
-            self.vp_sent_high = true;
-        }
-        if s == "cq" && pt == PulseType::High {
-            self.cq_sent_high = true;
+        if let Some(trace) = &mut self.trace {
+            trace.record(TraceEvent {
+                press: self.n_presses,
+                seq: self.n_pulses_this_press,
+                from: s.clone(),
+                pulse_type: pt.clone(),
+                to: r.clone(),
+            });
         }
+        self.n_pulses_this_press += 1;
 
         (pt, s, r)
     }
 }
 
 #[derive(PartialEq, Eq, Debug, Clone)]
-enum PulseType {
+pub enum PulseType {
     High,
     Low,
 }
@@ -223,15 +303,9 @@ impl fmt::Display for PulseType {
     }
 }
 
-#[derive(PartialEq, Eq, Debug)]
-enum ModuleType {
-    FlipFlop(bool),
-    Conjunction(HashMap<String, PulseType>),
-    Broadcaster,
-}
-
+#[derive(Debug, Clone)]
 struct Module {
-    type_: ModuleType,
+    component: Box<dyn Component>,
     name: String,
     destinations: Vec<String>,
 }
@@ -242,74 +316,51 @@ impl Module {
         pulse_type: PulseType,
         src: String,
     ) -> Vec<(PulseType, String, String)> {
-        if let ModuleType::FlipFlop(is_on) = &mut self.type_ {
-            if pulse_type == PulseType::Low {
-                let pt = if *is_on {
-                    PulseType::Low
-                } else {
-                    PulseType::High
-                };
-                let to_send: Vec<(PulseType, String, String)> = self
-                    .destinations
-                    .iter()
-                    .map(|dest| (pt.clone(), self.name.clone(), dest.clone()))
-                    .collect();
-                *is_on = !(*is_on);
-                return to_send;
-            } else {
-                return Vec::new();
-            }
-        }
-
-        if let ModuleType::Conjunction(memory) = &mut self.type_ {
-            *memory.get_mut(&src).unwrap() = pulse_type;
-            let pt = if memory.values().all(|x| *x == PulseType::High) {
-                PulseType::Low
-            } else {
-                PulseType::High
-            };
-            return self
+        match self.component.process_pulse(pulse_type, &src) {
+            Some(pt) => self
                 .destinations
                 .iter()
                 .map(|dest| (pt.clone(), self.name.clone(), dest.clone()))
-                .collect();
-        }
-
-        if self.type_ == ModuleType::Broadcaster {
-            return self
-                .destinations
-                .iter()
-                .map(|dest| (pulse_type.clone(), self.name.clone(), dest.clone()))
-                .collect();
+                .collect(),
+            None => Vec::new(),
         }
+    }
+}
 
-        panic!("ahhh!")
+/// Modules are equal if they are wired the same way and their components
+/// are of the same kind and in the same state
+impl PartialEq for Module {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+            && self.destinations == other.destinations
+            && self.component.kind() == other.component.kind()
+            && self.component.state() == other.component.state()
     }
 }
 
-fn parse_module(s: String) -> Module {
+/// Parse one line of the input, resolving the module's prefix with `registry`
+fn parse_module(s: String, registry: &ComponentRegistry) -> Module {
     let captures = INPUT_RE.captures(&s).unwrap();
     let type_and_name = captures["name"].to_owned();
-    let (type_, name) = match type_and_name.chars().next().unwrap() {
-        '%' => (ModuleType::FlipFlop(false), type_and_name[1..].to_string()),
-        '&' => (
-            ModuleType::Conjunction(HashMap::new()),
-            type_and_name[1..].to_string(),
-        ),
-        _ => {
+    let prefix = type_and_name.chars().next().unwrap();
+    let (component, name) = match registry.build(prefix) {
+        Some(component) => (component, type_and_name[prefix.len_utf8()..].to_string()),
+        None => {
             assert_eq!(type_and_name, "broadcaster");
-            (ModuleType::Broadcaster, type_and_name.to_string())
+            (
+                Box::new(Broadcaster) as Box<dyn Component>,
+                type_and_name.to_string(),
+            )
         }
     };
 
     let destinations: Vec<String> = captures["destinations"]
-        .to_owned()
         .split(", ")
         .map(|x| x.to_string())
         .collect();
 
     Module {
-        type_,
+        component,
         name,
         destinations,
     }
@@ -318,32 +369,42 @@ fn parse_module(s: String) -> Module {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use components::{Conjunction, Counter, FlipFlop, OutputSink};
+
+    fn component<T: Component>(module: &Module) -> &T {
+        let component: &dyn std::any::Any = &*module.component;
+        component.downcast_ref::<T>().unwrap()
+    }
 
     #[test]
     fn test_flip_flop() {
         let mut module = Module {
-            type_: ModuleType::FlipFlop(true),
+            component: Box::new(FlipFlop { is_on: true }),
             name: "foo".to_string(),
             destinations: vec!["bar".to_string(), "baz".to_string()],
         };
 
         let sent = module.process_pulse(PulseType::Low, "zap".to_string());
-        assert_eq!(module.type_, ModuleType::FlipFlop(false));
+        assert_eq!(component::<FlipFlop>(&module), &FlipFlop { is_on: false });
         assert_eq!(sent.len(), 2);
     }
 
     #[test]
     fn test_conjunction() {
         let mut module = Module {
-            type_: ModuleType::Conjunction(HashMap::from([("bar".to_string(), PulseType::Low)])),
+            component: Box::new(Conjunction {
+                memory: HashMap::from([("bar".to_string(), PulseType::Low)]),
+            }),
             name: "foo".to_string(),
             destinations: vec!["baz".to_string()],
         };
 
         let sent = module.process_pulse(PulseType::High, "bar".to_string());
         assert_eq!(
-            module.type_,
-            ModuleType::Conjunction(HashMap::from([("bar".to_string(), PulseType::High),]))
+            component::<Conjunction>(&module),
+            &Conjunction {
+                memory: HashMap::from([("bar".to_string(), PulseType::High),])
+            }
         );
         assert_eq!(sent.len(), 1);
         assert_eq!(
@@ -351,4 +412,71 @@ mod tests {
             (PulseType::Low, "foo".to_string(), "baz".to_string())
         );
     }
+
+    #[test]
+    fn test_parse_module() {
+        let module = parse_module("%a -> inv, con".to_string(), &ComponentRegistry::default());
+        assert_eq!(module.name, "a");
+        assert_eq!(module.component.kind(), "flip-flop");
+        assert_eq!(module.destinations, vec!["inv", "con"]);
+
+        let module = parse_module(
+            "broadcaster -> a".to_string(),
+            &ComponentRegistry::default(),
+        );
+        assert_eq!(module.component.kind(), "broadcaster");
+
+        let module = parse_module("#c -> rx".to_string(), &ComponentRegistry::default());
+        assert_eq!(module.name, "c");
+        assert_eq!(component::<Counter>(&module).modulus, 2);
+    }
+
+    #[test]
+    fn test_custom_components() {
+        let mut registry = ComponentRegistry::default();
+        registry.register('#', || Box::new(Counter::new(3)));
+
+        let modules: HashMap<String, Module> = [
+            "broadcaster -> inv, delay, count",
+            "!inv -> out",
+            "~delay -> late",
+            "#count -> rx",
+            "@out -> rx",
+            "@late -> rx",
+        ]
+        .into_iter()
+        .map(|line| parse_module(line.to_string(), &registry))
+        .map(|module| (module.name.clone(), module))
+        .collect();
+
+        let mut module_board = ModuleBoard::new(modules);
+        for _ in 0..3 {
+            module_board.push_button();
+        }
+
+        let out = component::<OutputSink>(&module_board.modules["out"]);
+        assert_eq!((out.n_low_received, out.n_high_received), (0, 3));
+        let late = component::<OutputSink>(&module_board.modules["late"]);
+        assert_eq!((late.n_low_received, late.n_high_received), (3, 0));
+        assert_eq!(
+            component::<Counter>(&module_board.modules["count"]).count,
+            0
+        );
+        // 3 presses: button, broadcaster x3, inverter, delay, and one counter pulse
+        assert_eq!(module_board.n_high_pulses_sent, 3 + 1);
+
+        // a sink's tallies are part of its state
+        let fresh_out = parse_module("@out -> rx".to_string(), &registry);
+        assert_ne!(module_board.modules["out"], fresh_out);
+        assert_ne!(
+            module_board.modules["late"].component.state(),
+            fresh_out.component.state()
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "a counter's modulus must be at least 1")]
+    fn test_counter_modulus_zero() {
+        Counter::new(0);
+    }
 }
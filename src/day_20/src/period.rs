@@ -0,0 +1,380 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::ModuleBoard;
+
+/// A strongly connected group of modules (e.g. a chain of flip-flops and
+/// the conjunction that resets them) whose output feeds conjunctions outside
+/// of the group
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Subcircuit {
+    /// Sorted by name
+    pub modules: Vec<String>,
+    /// The conjunctions outside of the subcircuit that it sends pulses to
+    pub feeds: Vec<String>,
+}
+
+impl ModuleBoard {
+    /// Every module's component state, ordered by module name
+    fn canonical_state(&self) -> Vec<(&String, Vec<u8>)> {
+        let mut result: Vec<(&String, Vec<u8>)> = self
+            .modules
+            .iter()
+            .map(|(name, module)| (name, module.component.state()))
+            .collect();
+        result.sort();
+        result
+    }
+
+    /// A hash of every flip-flop's and conjunction's memory that doesn't
+    /// depend on the order modules were parsed or stored in. This is 64-bit
+    /// FNV-1a over the length-prefixed names and states, so the same state
+    /// hashes the same with any build of the program.
+    pub fn state_hash(&self) -> u64 {
+        let mut bytes: Vec<u8> = Vec::new();
+        for (name, state) in self.canonical_state() {
+            bytes.extend((name.len() as u64).to_le_bytes());
+            bytes.extend(name.as_bytes());
+            bytes.extend((state.len() as u64).to_le_bytes());
+            bytes.extend(state);
+        }
+        fnv_1a(&bytes)
+    }
+
+    fn subcircuit_state(&self, subcircuit: &Subcircuit) -> Vec<Vec<u8>> {
+        subcircuit
+            .modules
+            .iter()
+            .map(|name| self.modules[name].component.state())
+            .collect()
+    }
+
+    /// The strongly connected components of the module graph that feed a
+    /// conjunction outside of themselves. Only components whose inputs from
+    /// outside come (transitively) from stateless modules are returned: they
+    /// see the exact same pulses on every press, so their state after a press
+    /// depends only on their state before it.
+    pub fn subcircuits(&self) -> Vec<Subcircuit> {
+        let mut result: Vec<Subcircuit> = Vec::new();
+        for component in strongly_connected_components(&self.modules_graph()) {
+            let members: HashSet<&String> = component.iter().collect();
+            let is_cycle = component.len() > 1
+                || self.modules[&component[0]]
+                    .destinations
+                    .contains(&component[0]);
+            if !is_cycle {
+                continue;
+            }
+
+            let mut feeds: Vec<String> = component
+                .iter()
+                .flat_map(|name| self.modules[name].destinations.iter())
+                .filter(|dest| !members.contains(dest))
+                .filter(|dest| {
+                    self.modules
+                        .get(*dest)
+                        .is_some_and(|module| module.component.kind() == "conjunction")
+                })
+                .cloned()
+                .collect();
+            feeds.sort();
+            feeds.dedup();
+
+            if !feeds.is_empty() && self.driven_by_stateless_modules(&members) {
+                let mut modules = component;
+                modules.sort();
+                result.push(Subcircuit { modules, feeds });
+            }
+        }
+
+        result.sort_by(|a, b| a.modules.cmp(&b.modules));
+        result
+    }
+
+    fn modules_graph(&self) -> HashMap<&String, Vec<&String>> {
+        self.modules
+            .iter()
+            .map(|(name, module)| {
+                (
+                    name,
+                    module
+                        .destinations
+                        .iter()
+                        .filter(|dest| self.modules.contains_key(*dest))
+                        .collect(),
+                )
+            })
+            .collect()
+    }
+
+    /// Are all of the modules upstream of `members` (outside of it) stateless?
+    fn driven_by_stateless_modules(&self, members: &HashSet<&String>) -> bool {
+        let mut sources: HashMap<&String, Vec<&String>> = HashMap::new();
+        for (name, module) in self.modules.iter() {
+            for dest in module.destinations.iter() {
+                sources.entry(dest).or_default().push(name);
+            }
+        }
+
+        let mut visited: HashSet<&String> = HashSet::new();
+        let mut to_visit: Vec<&String> = members.iter().cloned().collect();
+        while let Some(name) = to_visit.pop() {
+            for source in sources.get(name).into_iter().flatten() {
+                if members.contains(source) || !visited.insert(source) {
+                    continue;
+                }
+                if !self.modules[*source].component.state().is_empty() {
+                    return false;
+                }
+                to_visit.push(source);
+            }
+        }
+        true
+    }
+
+    /// Press the button on a copy of this board until each subcircuit is back
+    /// in the state it is in now. A subcircuit that comes back after `n`
+    /// presses repeats with period `n` forever, so this is exact rather than
+    /// inferred from sampled pulses. If any subcircuit doesn't come back
+    /// within `max_presses`, those subcircuits are the error.
+    pub fn subcircuit_periods(
+        &self,
+        max_presses: usize,
+    ) -> Result<Vec<(Subcircuit, usize)>, Vec<Subcircuit>> {
+        let subcircuits = self.subcircuits();
+        let initial_states: Vec<Vec<Vec<u8>>> = subcircuits
+            .iter()
+            .map(|subcircuit| self.subcircuit_state(subcircuit))
+            .collect();
+        let mut periods: Vec<Option<usize>> = vec![None; subcircuits.len()];
+
+        let mut board = ModuleBoard::new(self.modules.clone());
+        for nth_press in 1..=max_presses {
+            board.push_button();
+            for (idx, subcircuit) in subcircuits.iter().enumerate() {
+                if periods[idx].is_none()
+                    && board.subcircuit_state(subcircuit) == initial_states[idx]
+                {
+                    periods[idx] = Some(nth_press);
+                }
+            }
+            if periods.iter().all(|period| period.is_some()) {
+                break;
+            }
+        }
+
+        if periods.iter().all(|period| period.is_some()) {
+            Ok(subcircuits
+                .into_iter()
+                .zip(periods)
+                .map(|(subcircuit, period)| (subcircuit, period.unwrap()))
+                .collect())
+        } else {
+            Err(subcircuits
+                .into_iter()
+                .zip(periods)
+                .filter(|(_, period)| period.is_none())
+                .map(|(subcircuit, _)| subcircuit)
+                .collect())
+        }
+    }
+
+    /// The conjunction that sends to `name`, if it is the only module
+    /// sending to `name` and it is a conjunction
+    pub fn conjunction_before(&self, name: &str) -> Option<&String> {
+        let sources: Vec<&String> = self
+            .modules
+            .iter()
+            .filter(|(_, module)| module.destinations.iter().any(|dest| dest == name))
+            .map(|(source, _)| source)
+            .collect();
+        match sources[..] {
+            [source] if self.modules[source].component.kind() == "conjunction" => Some(source),
+            _ => None,
+        }
+    }
+
+    /// Can a pulse sent to any of `from` eventually reach module `to`?
+    pub fn reaches(&self, from: &[String], to: &str) -> bool {
+        let mut visited: HashSet<&String> = HashSet::new();
+        let mut to_visit: Vec<&String> = from.iter().collect();
+        while let Some(name) = to_visit.pop() {
+            if name == to {
+                return true;
+            }
+            if !visited.insert(name) {
+                continue;
+            }
+            if let Some(module) = self.modules.get(name) {
+                to_visit.extend(module.destinations.iter());
+            }
+        }
+        false
+    }
+}
+
+/// Tarjan's algorithm
+fn fnv_1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in bytes {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+fn strongly_connected_components(graph: &HashMap<&String, Vec<&String>>) -> Vec<Vec<String>> {
+    struct Tarjan<'a, 'b> {
+        graph: &'b HashMap<&'a String, Vec<&'a String>>,
+        index: HashMap<&'a String, usize>,
+        low_link: HashMap<&'a String, usize>,
+        stack: Vec<&'a String>,
+        on_stack: HashSet<&'a String>,
+        result: Vec<Vec<String>>,
+    }
+
+    impl<'a, 'b> Tarjan<'a, 'b> {
+        fn visit(&mut self, node: &'a String) {
+            let idx = self.index.len();
+            self.index.insert(node, idx);
+            self.low_link.insert(node, idx);
+            self.stack.push(node);
+            self.on_stack.insert(node);
+
+            for next in self.graph[node].iter() {
+                if !self.index.contains_key(*next) {
+                    self.visit(next);
+                    let low = self.low_link[node].min(self.low_link[*next]);
+                    self.low_link.insert(node, low);
+                } else if self.on_stack.contains(*next) {
+                    let low = self.low_link[node].min(self.index[*next]);
+                    self.low_link.insert(node, low);
+                }
+            }
+
+            if self.low_link[node] == self.index[node] {
+                let mut component: Vec<String> = Vec::new();
+                loop {
+                    let member = self.stack.pop().unwrap();
+                    self.on_stack.remove(member);
+                    component.push(member.clone());
+                    if member == node {
+                        break;
+                    }
+                }
+                self.result.push(component);
+            }
+        }
+    }
+
+    let mut tarjan = Tarjan {
+        graph,
+        index: HashMap::new(),
+        low_link: HashMap::new(),
+        stack: Vec::new(),
+        on_stack: HashSet::new(),
+        result: Vec::new(),
+    };
+    let mut nodes: Vec<&&String> = graph.keys().collect();
+    nodes.sort();
+    for node in nodes {
+        if !tarjan.index.contains_key(*node) {
+            tarjan.visit(node);
+        }
+    }
+    tarjan.result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{module_start_up, PulseType};
+    use shared::input::AocBufReader;
+
+    fn board(path: &str) -> ModuleBoard {
+        ModuleBoard::new(module_start_up(AocBufReader::from_string(path)))
+    }
+
+    #[test]
+    fn test_state_hash() {
+        let mut module_board = board("inputs/test.txt");
+        let initial = module_board.state_hash();
+        assert_eq!(initial, board("inputs/test.txt").state_hash());
+
+        module_board.push_button();
+        assert_ne!(module_board.state_hash(), initial);
+
+        // the example circuit cycles back to its start up state every 4 presses
+        for _ in 0..3 {
+            module_board.push_button();
+        }
+        assert_eq!(module_board.state_hash(), initial);
+    }
+
+    #[test]
+    fn test_fnv_1a() {
+        assert_eq!(fnv_1a(b""), 0xcbf29ce484222325);
+        assert_eq!(fnv_1a(b"a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(fnv_1a(b"foobar"), 0x85944171f73967e8);
+    }
+
+    #[test]
+    fn test_subcircuits() {
+        let module_board = board("inputs/test_2.txt");
+        let subcircuits = module_board.subcircuits();
+
+        assert_eq!(
+            subcircuits,
+            vec![
+                Subcircuit {
+                    modules: ["a0", "a1", "a2", "ca"].map(String::from).to_vec(),
+                    feeds: vec!["ia".to_string()],
+                },
+                Subcircuit {
+                    modules: ["b0", "b1", "b2", "cb"].map(String::from).to_vec(),
+                    feeds: vec!["ib".to_string()],
+                },
+            ]
+        );
+
+        // the example circuit has no loops at all
+        assert!(board("inputs/test.txt").subcircuits().is_empty());
+    }
+
+    #[test]
+    fn test_subcircuit_periods() {
+        let module_board = board("inputs/test_2.txt");
+        let periods: Vec<usize> = module_board
+            .subcircuit_periods(1000)
+            .unwrap()
+            .into_iter()
+            .map(|(_, period)| period)
+            .collect();
+        assert_eq!(periods, vec![5, 7]);
+
+        // the b subcircuit hasn't come back yet after 6 presses
+        let missing = module_board.subcircuit_periods(6).unwrap_err();
+        assert_eq!(missing.len(), 1);
+        assert_eq!(missing[0].modules[0], "b0");
+
+        let out = module_board.conjunction_before("rx").unwrap();
+        assert_eq!(out, "out");
+        assert!(module_board.reaches(&["ia".to_string()], out));
+        assert!(!module_board.reaches(&["ia".to_string()], "b0"));
+        assert_eq!(module_board.conjunction_before("out"), None);
+
+        // rx first hears a low pulse at the least common multiple
+        let mut module_board = board("inputs/test_2.txt");
+        module_board.enable_trace();
+        for _ in 0..35 {
+            module_board.push_button();
+        }
+        let first_low_to_rx = module_board
+            .trace
+            .as_ref()
+            .unwrap()
+            .filter_module("rx")
+            .find(|event| event.pulse_type == PulseType::Low)
+            .map(|event| event.press);
+        assert_eq!(first_low_to_rx, Some(35));
+    }
+}
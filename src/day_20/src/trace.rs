@@ -0,0 +1,328 @@
+use std::collections::HashMap;
+use std::fmt::{self, Write};
+
+use crate::{Module, ModuleBoard, PulseType};
+
+/// A single pulse: the `seq`th pulse sent during button press `press`
+/// (both counted the way `ModuleBoard` counts them: presses from 1, pulses
+/// within a press from 0)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceEvent {
+    pub press: usize,
+    pub seq: usize,
+    pub from: String,
+    pub pulse_type: PulseType,
+    pub to: String,
+}
+
+/// Every pulse sent by a `ModuleBoard` since tracing was enabled, in the
+/// order they were processed
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Trace {
+    events: Vec<TraceEvent>,
+}
+
+impl Trace {
+    pub fn record(&mut self, event: TraceEvent) {
+        self.events.push(event);
+    }
+
+    pub fn events(&self) -> &[TraceEvent] {
+        &self.events
+    }
+
+    /// Pulses sent from or to `module`
+    pub fn filter_module<'a>(&'a self, module: &'a str) -> impl Iterator<Item = &'a TraceEvent> {
+        self.events
+            .iter()
+            .filter(move |event| event.from == module || event.to == module)
+    }
+
+    pub fn filter_pulse_type<'a>(
+        &'a self,
+        pulse_type: &'a PulseType,
+    ) -> impl Iterator<Item = &'a TraceEvent> {
+        self.events
+            .iter()
+            .filter(move |event| event.pulse_type == *pulse_type)
+    }
+
+    /// One line per pulse, in the puzzle's notation: `1 0 button -low-> broadcaster`
+    pub fn to_text(&self) -> String {
+        let mut result = String::new();
+        for event in self.events.iter() {
+            writeln!(
+                result,
+                "{} {} {} -{}-> {}",
+                event.press, event.seq, event.from, event.pulse_type, event.to
+            )
+            .unwrap();
+        }
+        result
+    }
+
+    /// The inverse of `to_text`
+    pub fn from_text(s: &str) -> Self {
+        let events = s
+            .lines()
+            .map(|line| {
+                let parts: Vec<&str> = line.split_whitespace().collect();
+                let [press, seq, from, arrow, to] = parts[..] else {
+                    panic!("Failed to parse trace line: {}", line);
+                };
+                let pulse_type = match arrow {
+                    "-low->" => PulseType::Low,
+                    "-high->" => PulseType::High,
+                    _ => panic!("Failed to parse trace line: {}", line),
+                };
+                TraceEvent {
+                    press: press.parse::<usize>().unwrap(),
+                    seq: seq.parse::<usize>().unwrap(),
+                    from: from.to_string(),
+                    pulse_type,
+                    to: to.to_string(),
+                }
+            })
+            .collect();
+        Self { events }
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut result = "press,seq,from,pulse,to\n".to_string();
+        for event in self.events.iter() {
+            writeln!(
+                result,
+                "{},{},{},{},{}",
+                event.press, event.seq, event.from, event.pulse_type, event.to
+            )
+            .unwrap();
+        }
+        result
+    }
+}
+
+/// Why a trace can't be replayed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplayError {
+    /// The first pulse isn't pulse 0 of press 1
+    MissingStart { press: usize, seq: usize },
+    /// A pulse is missing (or out of order) before pulse `seq` of press `press`
+    Gap { press: usize, seq: usize },
+    /// The trace ends before the press to replay through
+    TooShort {
+        n_traced_presses: usize,
+        through_press: usize,
+    },
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::MissingStart { press, seq } => write!(
+                f,
+                "trace starts at press {press} pulse {seq}, not press 1 pulse 0"
+            ),
+            Self::Gap { press, seq } => {
+                write!(f, "trace isn't contiguous at press {press} pulse {seq}")
+            }
+            Self::TooShort {
+                n_traced_presses,
+                through_press,
+            } => write!(
+                f,
+                "trace only covers {n_traced_presses} presses, not {through_press}"
+            ),
+        }
+    }
+}
+
+impl Trace {
+    /// Check that the trace starts at the first pulse of the first press and
+    /// that every pulse follows on from the one before it: either the next
+    /// pulse of the same press or the first pulse of the next press
+    fn check_contiguous(&self) -> Result<(), ReplayError> {
+        let Some(first) = self.events.first() else {
+            return Ok(());
+        };
+        if (first.press, first.seq) != (1, 0) {
+            return Err(ReplayError::MissingStart {
+                press: first.press,
+                seq: first.seq,
+            });
+        }
+        for (prev, event) in self.events.iter().zip(self.events.iter().skip(1)) {
+            let next_in_press = (prev.press, prev.seq + 1);
+            let next_press = (prev.press + 1, 0);
+            if ![next_in_press, next_press].contains(&(event.press, event.seq)) {
+                return Err(ReplayError::Gap {
+                    press: event.press,
+                    seq: event.seq,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ModuleBoard {
+    /// Rebuild the board as it was right after press `through_press` by
+    /// delivering the traced pulses, in order, to freshly started-up
+    /// `modules`. The trace must be complete: every pulse from the first
+    /// press through `through_press`, as `--trace` writes it unfiltered.
+    pub fn replay(
+        modules: HashMap<String, Module>,
+        trace: &Trace,
+        through_press: usize,
+    ) -> Result<Self, ReplayError> {
+        trace.check_contiguous()?;
+        let n_traced_presses = trace.events.last().map_or(0, |event| event.press);
+        if through_press > n_traced_presses {
+            return Err(ReplayError::TooShort {
+                n_traced_presses,
+                through_press,
+            });
+        }
+
+        let mut board = Self::new(modules);
+        for event in trace
+            .events
+            .iter()
+            .take_while(|event| event.press <= through_press)
+        {
+            match event.pulse_type {
+                PulseType::Low => board.n_low_pulses_sent += 1,
+                PulseType::High => board.n_high_pulses_sent += 1,
+            }
+            if let Some(module) = board.modules.get_mut(&event.to) {
+                module.process_pulse(event.pulse_type.clone(), event.from.clone());
+            }
+        }
+        board.n_presses = through_press;
+        Ok(board)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::module_start_up;
+    use shared::input::AocBufReader;
+
+    fn traced_board(n_presses: usize) -> ModuleBoard {
+        let mut board = ModuleBoard::new(module_start_up(AocBufReader::from_string(
+            "inputs/test.txt",
+        )));
+        board.enable_trace();
+        for _ in 0..n_presses {
+            board.push_button();
+        }
+        board
+    }
+
+    #[test]
+    fn test_trace() {
+        let board = traced_board(4);
+        let trace = board.trace.as_ref().unwrap();
+
+        assert_eq!(
+            trace.events().len(),
+            board.n_low_pulses_sent + board.n_high_pulses_sent
+        );
+        assert_eq!(
+            trace.to_text().lines().take(4).collect::<Vec<&str>>(),
+            vec![
+                "1 0 button -low-> broadcaster",
+                "1 1 broadcaster -low-> a",
+                "1 2 a -high-> inv",
+                "1 3 a -high-> con",
+            ]
+        );
+        assert_eq!(
+            trace.filter_pulse_type(&PulseType::High).count(),
+            board.n_high_pulses_sent
+        );
+        assert!(trace
+            .filter_module("inv")
+            .all(|event| event.from == "inv" || event.to == "inv"));
+        assert_eq!(trace.filter_module("b").count(), 6);
+    }
+
+    #[test]
+    fn test_trace_export() {
+        let board = traced_board(2);
+        let trace = board.trace.as_ref().unwrap();
+
+        assert_eq!(Trace::from_text(&trace.to_text()), *trace);
+
+        let csv = trace.to_csv();
+        let mut lines = csv.lines();
+        assert_eq!(lines.next(), Some("press,seq,from,pulse,to"));
+        assert_eq!(lines.next(), Some("1,0,button,low,broadcaster"));
+        assert_eq!(lines.count(), trace.events().len() - 1);
+    }
+
+    #[test]
+    fn test_replay() {
+        let board = traced_board(1000);
+        let trace = board.trace.as_ref().unwrap();
+
+        for n_presses in [1, 2, 3, 4, 999, 1000] {
+            let expected = traced_board(n_presses);
+            let replayed = ModuleBoard::replay(
+                module_start_up(AocBufReader::from_string("inputs/test.txt")),
+                trace,
+                n_presses,
+            )
+            .unwrap();
+            assert_eq!(replayed.modules, expected.modules);
+            assert_eq!(replayed.n_low_pulses_sent, expected.n_low_pulses_sent);
+            assert_eq!(replayed.n_high_pulses_sent, expected.n_high_pulses_sent);
+        }
+    }
+
+    #[test]
+    fn test_replay_incomplete_trace() {
+        let board = traced_board(3);
+        let trace = board.trace.as_ref().unwrap();
+        let replay = |trace: &Trace, through_press: usize| {
+            ModuleBoard::replay(
+                module_start_up(AocBufReader::from_string("inputs/test.txt")),
+                trace,
+                through_press,
+            )
+            .map(|board| board.n_presses)
+        };
+        let without = |skip: fn(&TraceEvent) -> bool| {
+            let mut result = Trace::default();
+            for event in trace.events().iter().filter(|event| !skip(event)) {
+                result.record(event.clone());
+            }
+            result
+        };
+
+        assert_eq!(replay(trace, 3), Ok(3));
+        assert_eq!(
+            replay(trace, 4),
+            Err(ReplayError::TooShort {
+                n_traced_presses: 3,
+                through_press: 4
+            })
+        );
+        assert_eq!(
+            replay(&without(|event| event.press == 1), 2),
+            Err(ReplayError::MissingStart { press: 2, seq: 0 })
+        );
+        assert_eq!(
+            replay(&without(|event| (event.press, event.seq) == (2, 3)), 1),
+            Err(ReplayError::Gap { press: 2, seq: 4 })
+        );
+        assert_eq!(
+            replay(&without(|event| event.press == 2), 3),
+            Err(ReplayError::Gap { press: 3, seq: 0 })
+        );
+        assert_eq!(
+            replay(&without(|event| event.to != "inv"), 1),
+            Err(ReplayError::MissingStart { press: 1, seq: 2 })
+        );
+    }
+}
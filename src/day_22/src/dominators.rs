@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+
+/// The dominator tree of the support graph, rooted at the ground. Brick
+/// `u` dominates brick `v` if every chain of supports from the ground up
+/// to `v` passes through `u`, so the bricks that fall when `u` is
+/// disintegrated are exactly the ones below it in this tree.
+pub struct Dominators {
+    /// `idom[v]` for every brick; the ground is node `n_bricks`
+    idom: Vec<usize>,
+    children: Vec<Vec<usize>>,
+    n_dominated: Vec<usize>,
+}
+
+impl Dominators {
+    /// Bricks must be indexed bottom-up, i.e. every brick's supports
+    /// must have a smaller index than it does, as they do after
+    /// `Tetris::settle`. In that order the immediate dominator of a brick
+    /// is the nearest common dominator of everything it rests on.
+    pub fn new(k_supported_by_v: &HashMap<usize, Vec<usize>>) -> Self {
+        let n_bricks = k_supported_by_v.len();
+        let ground = n_bricks;
+        let mut idom: Vec<usize> = vec![ground; n_bricks + 1];
+        let mut depth: Vec<usize> = vec![0; n_bricks + 1];
+
+        for v in 0..n_bricks {
+            let supporters = &k_supported_by_v[&v];
+            assert!(
+                supporters.iter().all(|k| *k < v),
+                "bricks are not bottom-up"
+            );
+
+            let dominator = supporters
+                .iter()
+                .cloned()
+                .reduce(|a, b| common_dominator(a, b, &idom, &depth))
+                .unwrap_or(ground);
+            idom[v] = dominator;
+            depth[v] = depth[dominator] + 1;
+        }
+
+        let mut children: Vec<Vec<usize>> = vec![Vec::new(); n_bricks + 1];
+        let mut n_dominated: Vec<usize> = vec![0; n_bricks + 1];
+        for v in (0..n_bricks).rev() {
+            children[idom[v]].push(v);
+            n_dominated[idom[v]] += n_dominated[v] + 1;
+        }
+
+        Self {
+            idom,
+            children,
+            n_dominated,
+        }
+    }
+
+    /// The brick every support chain to `brick` passes through last,
+    /// or None if it is held up by the ground itself
+    pub fn immediate_dominator(&self, brick: usize) -> Option<usize> {
+        let ground = self.idom.len() - 1;
+        match self.idom[brick] {
+            d if d == ground => None,
+            d => Some(d),
+        }
+    }
+
+    /// How many other bricks fall if `brick` is disintegrated
+    pub fn n_would_fall(&self, brick: usize) -> usize {
+        self.n_dominated[brick]
+    }
+
+    /// The bricks that fall if `brick` is disintegrated, bottom-up
+    pub fn would_fall(&self, brick: usize) -> Vec<usize> {
+        let mut result: Vec<usize> = Vec::new();
+        let mut to_visit: Vec<usize> = self.children[brick].clone();
+        while let Some(v) = to_visit.pop() {
+            result.push(v);
+            to_visit.extend(self.children[v].iter());
+        }
+        result.sort();
+        result
+    }
+}
+
+fn common_dominator(mut a: usize, mut b: usize, idom: &[usize], depth: &[usize]) -> usize {
+    while a != b {
+        if depth[a] >= depth[b] {
+            a = idom[a];
+        } else {
+            b = idom[b];
+        }
+    }
+    a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dominators() {
+        // 0 and 1 sit on the ground, 2 rests on both, 3 rests on 2,
+        // 4 rests on 3 and 1, 5 rests on 4
+        let k_supported_by_v: HashMap<usize, Vec<usize>> = [
+            (0, vec![]),
+            (1, vec![]),
+            (2, vec![0, 1]),
+            (3, vec![2]),
+            (4, vec![3, 1]),
+            (5, vec![4]),
+        ]
+        .into_iter()
+        .collect();
+
+        let dominators = Dominators::new(&k_supported_by_v);
+        assert_eq!(dominators.immediate_dominator(2), None);
+        assert_eq!(dominators.immediate_dominator(3), Some(2));
+        assert_eq!(dominators.immediate_dominator(4), None);
+        assert_eq!(dominators.immediate_dominator(5), Some(4));
+
+        assert_eq!(dominators.would_fall(2), vec![3]);
+        assert_eq!(dominators.would_fall(4), vec![5]);
+        assert_eq!(dominators.would_fall(1), vec![]);
+        assert_eq!(
+            (0..6)
+                .map(|b| dominators.n_would_fall(b))
+                .collect::<Vec<usize>>(),
+            vec![0, 0, 1, 0, 1, 0]
+        );
+    }
+}
@@ -0,0 +1,210 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use crate::{Brick, Tetris};
+
+/// The corners of a unit-voxel cuboid are numbered by which of their
+/// coordinates are at the max end: bit 0 for x, bit 1 for y, bit 2 for z.
+/// Each face lists its corners counter-clockwise seen from outside.
+const FACES: [[usize; 4]; 6] = [
+    [0, 4, 6, 2],
+    [1, 3, 7, 5],
+    [0, 1, 5, 4],
+    [2, 6, 7, 3],
+    [0, 2, 3, 1],
+    [4, 5, 7, 6],
+];
+
+const PALETTE: [[u8; 3]; 8] = [
+    [230, 25, 75],
+    [60, 180, 75],
+    [255, 225, 25],
+    [0, 130, 200],
+    [245, 130, 48],
+    [145, 30, 180],
+    [70, 240, 240],
+    [240, 50, 230],
+];
+const SAFE_COLOR: [u8; 3] = [60, 200, 60];
+const UNSAFE_COLOR: [u8; 3] = [160, 160, 160];
+
+const SLICE_CHARS: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+impl Tetris {
+    /// The tower as an ASCII PLY mesh with one colored cuboid per brick.
+    /// With `highlight_safe`, bricks that are safe to disintegrate are
+    /// green and everything else is grey.
+    pub fn to_ply(&self, highlight_safe: bool) -> String {
+        let colors = self.brick_colors(highlight_safe);
+        let mut result = String::new();
+        writeln!(
+            result,
+            "ply\nformat ascii 1.0\ncomment day 22 settled tower"
+        )
+        .unwrap();
+        writeln!(result, "element vertex {}", self.bricks.len() * 8).unwrap();
+        for axis in ['x', 'y', 'z'] {
+            writeln!(result, "property float {axis}").unwrap();
+        }
+        for channel in ["red", "green", "blue"] {
+            writeln!(result, "property uchar {channel}").unwrap();
+        }
+        writeln!(result, "element face {}", self.bricks.len() * FACES.len()).unwrap();
+        writeln!(result, "property list uchar int vertex_indices\nend_header").unwrap();
+
+        for (brick, [r, g, b]) in self.bricks.iter().zip(colors.iter()) {
+            for [x, y, z] in corners(brick) {
+                writeln!(result, "{x} {y} {z} {r} {g} {b}").unwrap();
+            }
+        }
+        for brick_idx in 0..self.bricks.len() {
+            for face in FACES {
+                let [a, b, c, d] = face.map(|corner| brick_idx * 8 + corner);
+                writeln!(result, "4 {a} {b} {c} {d}").unwrap();
+            }
+        }
+        result
+    }
+
+    /// The tower as a Wavefront OBJ mesh, colored like `to_ply` using the
+    /// common `v x y z r g b` vertex color extension, with one object per brick
+    pub fn to_obj(&self, highlight_safe: bool) -> String {
+        let colors = self.brick_colors(highlight_safe);
+        let mut result = "# day 22 settled tower\n".to_string();
+        for (brick_idx, (brick, color)) in self.bricks.iter().zip(colors.iter()).enumerate() {
+            let [r, g, b] = color.map(|c| c as f32 / 255.0);
+            writeln!(result, "o brick_{}", brick.id).unwrap();
+            for [x, y, z] in corners(brick) {
+                writeln!(result, "v {x} {y} {z} {r:.3} {g:.3} {b:.3}").unwrap();
+            }
+            for face in FACES {
+                // OBJ vertex indices count from 1
+                let [a, b, c, d] = face.map(|corner| brick_idx * 8 + corner + 1);
+                writeln!(result, "f {a} {b} {c} {d}").unwrap();
+            }
+        }
+        result
+    }
+
+    /// Every z layer from the top down, seen from above with y running
+    /// down the page. Each voxel shows the last character of its brick's
+    /// id in base 62, so neighboring bricks can usually be told apart.
+    pub fn slices(&self) -> String {
+        let mut voxels: HashMap<(usize, usize, usize), usize> = HashMap::new();
+        for brick in self.bricks.iter() {
+            for coord in brick.bounds.voxels() {
+                voxels.insert((coord.x, coord.y, coord.z), brick.id);
+            }
+        }
+        let max_x = self
+            .bricks
+            .iter()
+            .map(|b| b.bounds.max[0])
+            .max()
+            .unwrap_or(0);
+        let max_y = self
+            .bricks
+            .iter()
+            .map(|b| b.bounds.max[1])
+            .max()
+            .unwrap_or(0);
+        let max_z = self
+            .bricks
+            .iter()
+            .map(|b| b.bounds.max[2])
+            .max()
+            .unwrap_or(0);
+
+        let mut result = String::new();
+        for z in (1..=max_z).rev() {
+            writeln!(result, "z = {z}").unwrap();
+            for y in 0..=max_y {
+                let row: String = (0..=max_x)
+                    .map(|x| match voxels.get(&(x, y, z)) {
+                        Some(id) => SLICE_CHARS[id % SLICE_CHARS.len()] as char,
+                        None => '.',
+                    })
+                    .collect();
+                writeln!(result, "{row}").unwrap();
+            }
+        }
+        result
+    }
+
+    fn brick_colors(&self, highlight_safe: bool) -> Vec<[u8; 3]> {
+        if highlight_safe {
+            self.safe_to_disintegrate()
+                .into_iter()
+                .map(|safe| if safe { SAFE_COLOR } else { UNSAFE_COLOR })
+                .collect()
+        } else {
+            self.bricks
+                .iter()
+                .map(|brick| PALETTE[brick.id % PALETTE.len()])
+                .collect()
+        }
+    }
+}
+
+/// The corners of the space `brick` fills, with voxel `(x, y, z)` filling
+/// the unit cube from `(x, y, z)` to `(x + 1, y + 1, z + 1)`
+fn corners(brick: &Brick) -> [[usize; 3]; 8] {
+    let (min, max) = (brick.bounds.min, brick.bounds.max);
+    let mut result = [[0; 3]; 8];
+    for (corner, point) in result.iter_mut().enumerate() {
+        for axis in 0..3 {
+            point[axis] = if corner & (1 << axis) == 0 {
+                min[axis]
+            } else {
+                max[axis] + 1
+            };
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{read_bricks, Tetris};
+    use shared::input::AocBufReader;
+
+    fn settled() -> Tetris {
+        let mut tetris = Tetris::new(read_bricks(AocBufReader::from_string("inputs/test.txt")));
+        tetris.settle();
+        tetris
+    }
+
+    #[test]
+    fn test_to_ply() {
+        let ply = settled().to_ply(true);
+        let (header, body) = ply.split_once("end_header\n").unwrap();
+        assert!(header.contains("element vertex 56\n"));
+        assert!(header.contains("element face 42\n"));
+
+        let lines: Vec<&str> = body.lines().collect();
+        assert_eq!(lines.len(), 56 + 42);
+        // brick 0 is 1,0,1~1,2,1 and is not safe to disintegrate
+        assert_eq!(lines[0], "1 0 1 160 160 160");
+        assert_eq!(lines[7], "2 3 2 160 160 160");
+        // brick 1 is safe
+        assert!(lines[8].ends_with(" 60 200 60"));
+        assert_eq!(lines[56], "4 0 4 6 2");
+        assert_eq!(lines[97], "4 52 53 55 54");
+    }
+
+    #[test]
+    fn test_to_obj() {
+        let obj = settled().to_obj(false);
+        assert_eq!(obj.lines().filter(|l| l.starts_with("o ")).count(), 7);
+        assert_eq!(obj.lines().filter(|l| l.starts_with("v ")).count(), 56);
+        assert!(obj.contains("o brick_6\nv 1 1 5 0.275 0.941 0.941\n"));
+        assert!(obj.ends_with("f 53 54 56 55\n"));
+    }
+
+    #[test]
+    fn test_slices() {
+        let slices = settled().slices();
+        assert!(slices.starts_with("z = 6\n...\n.6.\n...\nz = 5\n...\n.6.\n...\nz = 4\n...\n555\n"));
+        assert!(slices.ends_with("z = 2\n111\n...\n222\nz = 1\n.0.\n.0.\n.0.\n"));
+    }
+}
@@ -0,0 +1,56 @@
+use std::collections::HashMap;
+
+/// The settled tower seen from above: for every `(x, y)` column, the z of
+/// its highest settled voxel and the index of the brick that voxel is in.
+/// Columns that nothing has settled in are at the ground, z = 0.
+#[derive(Default)]
+pub struct HeightMap {
+    tops: HashMap<(usize, usize), (usize, usize)>,
+}
+
+impl HeightMap {
+    /// The highest settled z under `footprint` and the bricks whose tops are
+    /// at that height, which are exactly the bricks that a brick dropped onto
+    /// `footprint` comes to rest on
+    pub fn landing(&self, footprint: &[(usize, usize)]) -> (usize, Vec<usize>) {
+        let mut z_max: usize = 0;
+        let mut supporters: Vec<usize> = Vec::new();
+        for (z, brick_idx) in footprint.iter().filter_map(|xy| self.tops.get(xy)) {
+            if *z > z_max {
+                z_max = *z;
+                supporters.clear();
+            }
+            if *z == z_max && !supporters.contains(brick_idx) {
+                supporters.push(*brick_idx);
+            }
+        }
+        (z_max, supporters)
+    }
+
+    /// Record that brick `brick_idx` now tops every column of `footprint` at `z_top`
+    pub fn place(&mut self, footprint: &[(usize, usize)], z_top: usize, brick_idx: usize) {
+        for xy in footprint {
+            self.tops.insert(*xy, (z_top, brick_idx));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_landing() {
+        let mut height_map = HeightMap::default();
+        assert_eq!(height_map.landing(&[(0, 0), (1, 0)]), (0, vec![]));
+
+        height_map.place(&[(0, 0), (1, 0)], 1, 0);
+        height_map.place(&[(2, 0)], 1, 1);
+        height_map.place(&[(0, 1)], 3, 2);
+        assert_eq!(
+            height_map.landing(&[(1, 0), (2, 0), (3, 0)]),
+            (1, vec![0, 1])
+        );
+        assert_eq!(height_map.landing(&[(0, 0), (0, 1)]), (3, vec![2]));
+    }
+}
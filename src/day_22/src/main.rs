@@ -1,12 +1,40 @@
 use std::collections::{HashMap, HashSet};
+use std::fmt;
 
-use shared::coords3d::U3Coord;
+use shared::coords3d::Cuboid;
 use shared::input::AocBufReader;
-use shared::range::Range;
 
 use itertools::Itertools;
 
+mod dominators;
+mod export;
+mod height_map;
+mod validate;
+
+use dominators::Dominators;
+use height_map::HeightMap;
+use validate::parse_bricks;
+
 fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        let mut tetris = Tetris::new(read_bricks(AocBufReader::from_string("inputs/part_1.txt")));
+        tetris.settle();
+        let highlight_safe = args.iter().any(|arg| arg == "--safe");
+        match args[0].as_str() {
+            "--obj" => print!("{}", tetris.to_obj(highlight_safe)),
+            "--ply" => print!("{}", tetris.to_ply(highlight_safe)),
+            "--slices" => print!("{}", tetris.slices()),
+            "--views" => {
+                tetris.print_view_along_x();
+                tetris.print_view_along_y();
+                println!();
+            }
+            brick_id => println!("{}", tetris.report(brick_id.parse::<usize>().unwrap())),
+        }
+        return;
+    }
+
     let result = part_1(AocBufReader::from_string("inputs/part_1.txt"));
     println!("part 1: {result}");
 
@@ -17,84 +45,131 @@ fn main() {
 fn part_1(reader: AocBufReader) -> usize {
     let mut tetris = Tetris::new(read_bricks(reader));
     tetris.settle();
-    let (k_supports_v, k_supported_by_v) = tetris.dependency_graph();
-    k_supports_v
-        .iter()
-        .filter(|(_, supported)| {
-            supported
-                .iter()
-                .all(|supported| k_supported_by_v[supported].len() > 1)
-        })
+    tetris
+        .safe_to_disintegrate()
+        .into_iter()
+        .filter(|safe| *safe)
         .count()
 }
 
 fn part_2(reader: AocBufReader) -> usize {
     let mut tetris = Tetris::new(read_bricks(reader));
     tetris.settle();
-    let n_bricks = tetris.bricks.len();
-
-    let mut result: usize = 0;
-    for idx in 0..n_bricks {
-        let mut copy = tetris.remove_idx_and_clone(idx);
-        result += copy.settle();
-    }
+    let dominators = Dominators::new(&tetris.k_supported_by_v);
 
-    result
+    (0..tetris.bricks.len())
+        .map(|idx| dominators.n_would_fall(idx))
+        .sum()
 }
 
 struct Tetris {
     bricks: Vec<Brick>,
+    /// Which bricks each brick rests on top of, and the reverse. Both are
+    /// recorded by `settle` and indexed by position in `bricks`.
+    k_supports_v: HashMap<usize, Vec<usize>>,
+    k_supported_by_v: HashMap<usize, Vec<usize>>,
 }
 
 impl Tetris {
     fn new(bricks: Vec<Brick>) -> Self {
-        Self { bricks }
+        Self {
+            bricks,
+            k_supports_v: HashMap::new(),
+            k_supported_by_v: HashMap::new(),
+        }
     }
 
-    /// All bricks fall to the lost level possible
+    /// All bricks fall to the lost level possible. Bricks are dropped from
+    /// the bottom up onto a `HeightMap` of the tower so far, so each brick
+    /// only looks at the columns under its own footprint.
     fn settle(&mut self) -> usize {
-        self.bricks.sort_by_key(|brick| brick.z.start);
+        self.bricks.sort_by_key(|brick| brick.z_min());
+        let n_bricks = self.bricks.len();
+        self.k_supports_v = (0..n_bricks).map(|idx| (idx, Vec::new())).collect();
+        self.k_supported_by_v = (0..n_bricks).map(|idx| (idx, Vec::new())).collect();
 
         let mut n_fell: usize = 0;
-        let mut settled_bricks: Vec<&Brick> = Vec::new();
-        for brick in self.bricks.iter_mut() {
-            if brick.settle(&settled_bricks) {
+        let mut height_map = HeightMap::default();
+        for (idx, brick) in self.bricks.iter_mut().enumerate() {
+            let footprint = brick.viewed_along_z();
+            let (z_below, supporters) = height_map.landing(&footprint);
+            if brick.fall_to(z_below + 1) {
                 n_fell += 1;
             }
-            settled_bricks.push(brick);
+            height_map.place(&footprint, brick.bounds.max[2], idx);
+
+            for supporter in supporters {
+                self.k_supports_v.get_mut(&supporter).unwrap().push(idx);
+                self.k_supported_by_v.get_mut(&idx).unwrap().push(supporter);
+            }
         }
 
+        if cfg!(debug_assertions) {
+            self.assert_settled();
+        }
         n_fell
     }
 
-    fn dependency_graph(&self) -> (HashMap<usize, Vec<usize>>, HashMap<usize, Vec<usize>>) {
-        let n_bricks = self.bricks.len();
-        let mut k_supports_v = HashMap::from(
-            (0..n_bricks)
-                .map(|idx| (idx, Vec::new()))
-                .collect::<HashMap<usize, Vec<usize>>>(),
-        );
-        let mut k_supported_by_v = HashMap::from(
-            (0..n_bricks)
-                .map(|idx| (idx, Vec::new()))
-                .collect::<HashMap<usize, Vec<usize>>>(),
-        );
-        for k_idx in 0..n_bricks {
-            k_supports_v.insert(k_idx, Vec::new());
-            for v_idx in 0..n_bricks {
-                if k_idx == v_idx {
-                    continue;
-                }
-                if self.bricks[k_idx].supports(&self.bricks[v_idx]) {
-                    k_supports_v.get_mut(&k_idx).unwrap().push(v_idx);
-                    k_supported_by_v.get_mut(&v_idx).unwrap().push(k_idx);
-                }
-            }
+    /// Panic unless every brick is on the ground or directly on top of
+    /// another, checked against the bricks' voxels rather than the
+    /// height map that settled them. This is O(volume), so `settle` only
+    /// runs it in debug builds.
+    fn assert_settled(&self) {
+        let occupied: HashSet<(usize, usize, usize)> = self
+            .bricks
+            .iter()
+            .flat_map(|brick| brick.bounds.voxels().map(|c| (c.x, c.y, c.z)))
+            .collect();
+        for brick in self.bricks.iter() {
+            let z = brick.z_min();
+            assert!(
+                z == 1
+                    || brick
+                        .viewed_along_z()
+                        .into_iter()
+                        .any(|(x, y)| occupied.contains(&(x, y, z - 1))),
+                "brick {} can still fall",
+                brick.id
+            );
         }
+    }
 
-        (k_supports_v, k_supported_by_v)
+    /// For each brick, whether everything it holds up rests on something else too
+    fn safe_to_disintegrate(&self) -> Vec<bool> {
+        (0..self.bricks.len())
+            .map(|idx| {
+                self.k_supports_v[&idx]
+                    .iter()
+                    .all(|supported| self.k_supported_by_v[supported].len() > 1)
+            })
+            .collect()
     }
 
+    /// The bricks that fall when brick `id` (its line in the input,
+    /// counting from 0) is disintegrated, along with what it touches
+    fn report(&self, id: usize) -> BrickReport {
+        let idx = self.bricks.iter().position(|brick| brick.id == id).unwrap();
+        let ids = |idxs: &[usize]| -> Vec<usize> {
+            let mut ids: Vec<usize> = idxs.iter().map(|idx| self.bricks[*idx].id).collect();
+            ids.sort();
+            ids
+        };
+
+        let dominators = Dominators::new(&self.k_supported_by_v);
+        BrickReport {
+            brick: self.bricks[idx].clone(),
+            rests_on: ids(&self.k_supported_by_v[&idx]),
+            holds_up: ids(&self.k_supports_v[&idx]),
+            falls_with: dominators
+                .immediate_dominator(idx)
+                .map(|dominator| self.bricks[dominator].id),
+            would_fall: ids(&dominators.would_fall(idx)),
+        }
+    }
+
+    /// Disintegrating brick `n` the slow way, by settling everything else
+    /// again; kept as a reference for `Dominators`
+    #[cfg(test)]
     fn remove_idx_and_clone(&self, n: usize) -> Self {
         let bricks: Vec<Brick> = self
             .bricks
@@ -104,7 +179,7 @@ impl Tetris {
             .map(|(_, brick)| brick.clone())
             .collect();
 
-        Self { bricks }
+        Self::new(bricks)
     }
 
     fn print_view_along_x(&self) {
@@ -112,14 +187,13 @@ impl Tetris {
         let yz: HashSet<(usize, usize)> = self
             .bricks
             .iter()
-            .map(|brick| brick.viewed_along_x())
-            .flatten()
+            .flat_map(|brick| brick.viewed_along_x())
             .collect();
 
         let max_y: usize = yz.iter().map(|(y, _)| *y).max().unwrap();
         let max_z: usize = yz.iter().map(|(_, z)| *z).max().unwrap();
         for z in (0..=max_z).rev() {
-            print!("\n");
+            println!();
             for y in 0..=max_y {
                 if z == 0 {
                     print!("-");
@@ -139,14 +213,13 @@ impl Tetris {
         let xz: HashSet<(usize, usize)> = self
             .bricks
             .iter()
-            .map(|brick| brick.viewed_along_y())
-            .flatten()
+            .flat_map(|brick| brick.viewed_along_y())
             .collect();
 
         let max_x: usize = xz.iter().map(|(x, _)| *x).max().unwrap();
         let max_z: usize = xz.iter().map(|(_, z)| *z).max().unwrap();
         for z in (0..=max_z).rev() {
-            print!("\n");
+            println!();
             for x in 0..=max_x {
                 if z == 0 {
                     print!("-");
@@ -162,17 +235,58 @@ impl Tetris {
     }
 }
 
-#[derive(Clone)]
+struct BrickReport {
+    brick: Brick,
+    rests_on: Vec<usize>,
+    holds_up: Vec<usize>,
+    /// The nearest brick whose loss would bring this one down too
+    falls_with: Option<usize>,
+    would_fall: Vec<usize>,
+}
+
+impl fmt::Display for BrickReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let join = |ids: &[usize]| -> String {
+            if ids.is_empty() {
+                "nothing".to_string()
+            } else {
+                ids.iter().map(|id| id.to_string()).join(", ")
+            }
+        };
+
+        let (min, max) = (self.brick.bounds.min, self.brick.bounds.max);
+        writeln!(
+            f,
+            "brick {} at {},{},{}~{},{},{}",
+            self.brick.id, min[0], min[1], min[2], max[0], max[1], max[2]
+        )?;
+        writeln!(f, "rests on: {}", join(&self.rests_on))?;
+        writeln!(f, "holds up: {}", join(&self.holds_up))?;
+        writeln!(
+            f,
+            "falls with: {}",
+            join(&self.falls_with.into_iter().collect::<Vec<usize>>())
+        )?;
+        write!(
+            f,
+            "{} would fall: {}",
+            self.would_fall.len(),
+            join(&self.would_fall)
+        )
+    }
+}
+
+#[derive(Clone, Debug)]
 struct Brick {
-    x: Range<usize>,
-    y: Range<usize>,
-    z: Range<usize>,
-    coords: Vec<U3Coord>,
+    /// The brick's line in the input, counting from 0
+    id: usize,
+    bounds: Cuboid<usize>,
 }
 
 impl Brick {
     /// A new brick whose input bounds are _inclusive_
     fn new(
+        id: usize,
         x_min: usize,
         x_max: usize,
         y_min: usize,
@@ -180,108 +294,133 @@ impl Brick {
         z_min: usize,
         z_max: usize,
     ) -> Self {
-        let coords: Vec<U3Coord> = (x_min..=x_max)
-            .cartesian_product((y_min..=y_max).cartesian_product(z_min..=z_max))
-            .map(|(x, (y, z))| U3Coord::new(x, y, z))
-            .collect();
-
         Self {
-            x: Range {
-                start: x_min,
-                end: x_max,
-            },
-            y: Range {
-                start: y_min,
-                end: y_max,
-            },
-            z: Range {
-                start: z_min,
-                end: z_max,
-            },
-            coords,
+            id,
+            bounds: Cuboid::new([x_min, y_min, z_min], [x_max, y_max, z_max]).unwrap(),
         }
     }
 
+    fn z_min(&self) -> usize {
+        self.bounds.min[2]
+    }
+
+    /// The `(x, y)` columns the brick occupies
     fn viewed_along_z(&self) -> Vec<(usize, usize)> {
-        let mut xy: Vec<(usize, usize)> = self.coords.iter().map(|c| (c.x, c.y)).collect();
-        xy.sort();
-        xy
+        self.bounds
+            .project(2)
+            .points()
+            .map(|[x, y]| (x, y))
+            .collect()
     }
 
     fn viewed_along_x(&self) -> Vec<(usize, usize)> {
-        let mut yz: Vec<(usize, usize)> = self.coords.iter().map(|c| (c.y, c.z)).collect();
-        yz.sort();
-        yz
+        self.bounds
+            .project(0)
+            .points()
+            .map(|[y, z]| (y, z))
+            .collect()
     }
 
     fn viewed_along_y(&self) -> Vec<(usize, usize)> {
-        let mut xz: Vec<(usize, usize)> = self.coords.iter().map(|c| (c.x, c.z)).collect();
-        xz.sort();
-        xz
-    }
-
-    fn settle(&mut self, settled_bricks: &Vec<&Brick>) -> bool {
-        let self_xy = self.viewed_along_z();
-        let settled_coords_under_self: Vec<&U3Coord> = settled_bricks
-            .iter()
-            .map(|brick| brick.coords.iter())
-            .flatten()
-            .filter(|coord| self_xy.contains(&(coord.x, coord.y)))
-            .collect();
-
-        let level_to_settle_to: usize =
-            match settled_coords_under_self.into_iter().map(|c| c.z).max() {
-                Some(z) => z + 1,
-                None => 1,
-            };
-
-        self.fall_to(level_to_settle_to)
+        self.bounds
+            .project(1)
+            .points()
+            .map(|[x, z]| (x, z))
+            .collect()
     }
 
     fn fall_to(&mut self, z: usize) -> bool {
-        let diff = self.z.start - z;
-        if diff == 0 {
+        if self.z_min() == z {
             false
         } else {
-            self.z.start -= diff;
-            self.z.end -= diff;
-            for coord in self.coords.iter_mut() {
-                coord.z -= diff;
-            }
+            self.bounds = self.bounds.moved_to(2, z);
             true
         }
     }
 
+    #[cfg(test)]
     fn supports(&self, other: &Self) -> bool {
-        self.coords
-            .iter()
-            .map(|coord| coord.z_plus().unwrap())
-            .any(|c| other.coords.contains(&c))
+        self.bounds.touches_below(&other.bounds, 2)
     }
 }
 
-fn read_bricks(reader: AocBufReader) -> Vec<Brick> {
-    reader
-        .into_iter()
-        .map(|line| {
-            let mut start_end = line.split("~");
-            let start = start_end.next().unwrap();
-            let end = start_end.next().unwrap();
-            let minima: Vec<usize> = start
-                .split(",")
-                .map(|x| x.parse::<usize>().unwrap())
-                .collect();
-            let maxima: Vec<usize> = end
-                .split(",")
-                .map(|x| x.parse::<usize>().unwrap())
-                .collect();
-
-            assert_eq!(minima.len(), 3);
-            assert_eq!(maxima.len(), 3);
-
-            Brick::new(
-                minima[0], maxima[0], minima[1], maxima[1], minima[2], maxima[2],
-            )
-        })
-        .collect()
+fn read_bricks(lines: impl Iterator<Item = String>) -> Vec<Brick> {
+    parse_bricks(lines).unwrap_or_else(|errors| {
+        panic!("Invalid input:\n{}", errors.iter().join("\n"));
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parts() {
+        assert_eq!(part_1(AocBufReader::from_string("inputs/test.txt")), 5);
+        assert_eq!(part_2(AocBufReader::from_string("inputs/test.txt")), 7);
+    }
+
+    #[test]
+    fn test_dominators_match_resettling() {
+        let mut tetris = Tetris::new(read_bricks(AocBufReader::from_string("inputs/test.txt")));
+        tetris.settle();
+        let dominators = Dominators::new(&tetris.k_supported_by_v);
+        for idx in 0..tetris.bricks.len() {
+            let mut copy = tetris.remove_idx_and_clone(idx);
+            assert_eq!(dominators.n_would_fall(idx), copy.settle());
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "brick 2 can still fall")]
+    fn test_assert_settled() {
+        let tetris = Tetris::new(read_bricks(AocBufReader::from_string("inputs/test.txt")));
+        tetris.assert_settled();
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid input:\nline 2: brick overlaps the brick on line 1")]
+    fn test_read_bricks_invalid() {
+        read_bricks(["1,0,1~1,2,1", "0,1,1~2,1,1"].map(String::from).into_iter());
+    }
+
+    #[test]
+    fn test_report() {
+        let mut tetris = Tetris::new(read_bricks(AocBufReader::from_string("inputs/test.txt")));
+        tetris.settle();
+        assert_eq!(
+            tetris.report(0).to_string(),
+            "brick 0 at 1,0,1~1,2,1\n\
+             rests on: nothing\n\
+             holds up: 1, 2\n\
+             falls with: nothing\n\
+             6 would fall: 1, 2, 3, 4, 5, 6"
+        );
+        assert_eq!(
+            tetris.report(5).to_string(),
+            "brick 5 at 0,1,4~2,1,4\n\
+             rests on: 3, 4\n\
+             holds up: 6\n\
+             falls with: 0\n\
+             1 would fall: 6"
+        );
+    }
+
+    #[test]
+    fn test_dependency_graph() {
+        let mut tetris = Tetris::new(read_bricks(AocBufReader::from_string("inputs/test.txt")));
+        assert_eq!(tetris.settle(), 5);
+
+        let (k_supports_v, k_supported_by_v) = (&tetris.k_supports_v, &tetris.k_supported_by_v);
+        assert_eq!(k_supports_v[&0], vec![1, 2]);
+        assert_eq!(k_supported_by_v[&3], vec![1, 2]);
+        for (k_idx, k) in tetris.bricks.iter().enumerate() {
+            for (v_idx, v) in tetris.bricks.iter().enumerate() {
+                assert_eq!(
+                    k_supports_v[&k_idx].contains(&v_idx),
+                    k_idx != v_idx && k.supports(v)
+                );
+            }
+        }
+    }
 }
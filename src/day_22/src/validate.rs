@@ -0,0 +1,194 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use itertools::Itertools;
+
+use crate::Brick;
+
+/// Something in the input that `Tetris` can't make sense of.
+/// Line numbers count from 1, like an editor.
+#[derive(Debug, PartialEq, Eq)]
+pub enum InputError {
+    Malformed { line: usize, text: String },
+    InvertedRange { line: usize, axis: char },
+    BelowGround { line: usize },
+    Overlap { line: usize, other_line: usize },
+}
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Malformed { line, text } => {
+                write!(f, "line {line}: expected x,y,z~x,y,z, got {text:?}")
+            }
+            Self::InvertedRange { line, axis } => {
+                write!(f, "line {line}: {axis} range ends before it starts")
+            }
+            Self::BelowGround { line } => write!(f, "line {line}: brick is below the ground"),
+            Self::Overlap { line, other_line } => {
+                write!(
+                    f,
+                    "line {line}: brick overlaps the brick on line {other_line}"
+                )
+            }
+        }
+    }
+}
+
+/// Parse every line into a brick, or report every problem with the input.
+/// Bricks that overlap are only looked for once each line parses.
+pub fn parse_bricks(lines: impl Iterator<Item = String>) -> Result<Vec<Brick>, Vec<InputError>> {
+    let mut errors: Vec<InputError> = Vec::new();
+    let mut bricks: Vec<Brick> = Vec::new();
+    for (id, text) in lines.enumerate() {
+        let line = id + 1;
+        let Some((minima, maxima)) = parse_corners(&text) else {
+            errors.push(InputError::Malformed { line, text });
+            continue;
+        };
+
+        let mut valid = true;
+        for (axis, name) in ['x', 'y', 'z'].into_iter().enumerate() {
+            if minima[axis] > maxima[axis] {
+                errors.push(InputError::InvertedRange { line, axis: name });
+                valid = false;
+            }
+        }
+        if minima[2].min(maxima[2]) < 1 {
+            errors.push(InputError::BelowGround { line });
+            valid = false;
+        }
+
+        if valid {
+            bricks.push(Brick::new(
+                id, minima[0], maxima[0], minima[1], maxima[1], minima[2], maxima[2],
+            ));
+        }
+    }
+
+    if errors.is_empty() {
+        errors.extend(overlaps(&bricks));
+    }
+    if errors.is_empty() {
+        Ok(bricks)
+    } else {
+        Err(errors)
+    }
+}
+
+fn parse_corners(text: &str) -> Option<([usize; 3], [usize; 3])> {
+    let (start, end) = text.split_once('~')?;
+    let parse = |corner: &str| -> Option<[usize; 3]> {
+        let values: Vec<usize> = corner
+            .split(',')
+            .map(|x| x.trim().parse::<usize>().ok())
+            .collect::<Option<Vec<usize>>>()?;
+        values.try_into().ok()
+    };
+    Some((parse(start)?, parse(end)?))
+}
+
+/// Every pair of bricks that share a voxel, found by claiming voxels one
+/// brick at a time so it costs the total volume rather than a pairwise scan.
+/// A voxel keeps every brick that claimed it, so when three or more bricks
+/// share one, every pair of them is reported.
+fn overlaps(bricks: &[Brick]) -> Vec<InputError> {
+    let mut claimed: HashMap<[usize; 3], Vec<usize>> = HashMap::new();
+    let mut pairs: HashSet<(usize, usize)> = HashSet::new();
+    for brick in bricks {
+        for voxel in brick.bounds.points() {
+            let owners = claimed.entry(voxel).or_default();
+            for other_id in owners.iter() {
+                pairs.insert((*other_id, brick.id));
+            }
+            owners.push(brick.id);
+        }
+    }
+
+    pairs
+        .into_iter()
+        .sorted_by_key(|(other_id, id)| (*id, *other_id))
+        .map(|(other_id, id)| InputError::Overlap {
+            line: id + 1,
+            other_line: other_id + 1,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn errors(lines: &[&str]) -> Vec<InputError> {
+        parse_bricks(lines.iter().map(|line| line.to_string())).unwrap_err()
+    }
+
+    #[test]
+    fn test_parse_bricks() {
+        let bricks = parse_bricks(["1,0,1~1,2,1", "0,0,2~2,0,2"].map(String::from).into_iter());
+        assert_eq!(bricks.unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_malformed_inverted_and_below_ground() {
+        assert_eq!(
+            errors(&["1,0,1~1,2", "2,0,1~1,0,0", "a,0,1~1,0,1"]),
+            vec![
+                InputError::Malformed {
+                    line: 1,
+                    text: "1,0,1~1,2".to_string()
+                },
+                InputError::InvertedRange { line: 2, axis: 'x' },
+                InputError::InvertedRange { line: 2, axis: 'z' },
+                InputError::BelowGround { line: 2 },
+                InputError::Malformed {
+                    line: 3,
+                    text: "a,0,1~1,0,1".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_overlaps() {
+        let errors = errors(&["0,0,1~2,0,1", "1,0,1~1,2,1", "5,5,5~5,5,5", "0,0,1~0,0,3"]);
+        assert_eq!(
+            errors,
+            vec![
+                InputError::Overlap {
+                    line: 2,
+                    other_line: 1
+                },
+                InputError::Overlap {
+                    line: 4,
+                    other_line: 1
+                },
+            ]
+        );
+        assert_eq!(
+            errors[0].to_string(),
+            "line 2: brick overlaps the brick on line 1"
+        );
+    }
+
+    #[test]
+    fn test_three_bricks_overlap() {
+        assert_eq!(
+            errors(&["0,0,1~2,0,1", "1,0,1~1,2,1", "1,0,1~1,0,3"]),
+            vec![
+                InputError::Overlap {
+                    line: 2,
+                    other_line: 1
+                },
+                InputError::Overlap {
+                    line: 3,
+                    other_line: 1
+                },
+                InputError::Overlap {
+                    line: 3,
+                    other_line: 2
+                },
+            ]
+        );
+    }
+}
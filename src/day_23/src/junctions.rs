@@ -0,0 +1,254 @@
+use std::collections::HashMap;
+use std::thread;
+
+use shared::coords::UCoord;
+
+use crate::Map;
+
+/// The longest hike: its length in steps and the junctions it passes
+/// through, from the start to the end
+#[derive(Debug, Clone)]
+pub struct Hike {
+    pub length: usize,
+    pub junctions: Vec<UCoord>,
+}
+
+/// The best path a search has found so far, by junction index
+#[derive(Default)]
+struct Best {
+    length: usize,
+    path: Vec<usize>,
+}
+
+impl Best {
+    fn offer(&mut self, length: usize, path: &[usize]) {
+        if length > self.length || self.path.is_empty() {
+            self.length = length;
+            self.path = path.to_vec();
+        }
+    }
+
+    fn merge(&mut self, other: Best) {
+        if !other.path.is_empty() {
+            self.offer(other.length, &other.path);
+        }
+    }
+}
+
+/// The compressed junction graph renumbered to dense indices, so that a
+/// path can carry the set of junctions it has visited as a `u64` bitmask
+pub struct JunctionGraph {
+    junctions: Vec<UCoord>,
+    start: usize,
+    end: usize,
+    /// `adjacency[junction]` holds `(neighbor, path length)` for every
+    /// junction reachable from `junction` without passing another junction
+    adjacency: Vec<Vec<(usize, usize)>>,
+    /// If only one junction leads to the exit, that junction and its
+    /// distance to the exit. A path that reaches it must go straight to the
+    /// exit: the only way out is already behind it.
+    last_before_end: Option<(usize, usize)>,
+}
+
+impl JunctionGraph {
+    pub fn new(map: &Map) -> Self {
+        let mut junctions: Vec<&UCoord> =
+            map.edges.keys().flat_map(|(from, to)| [from, to]).collect();
+        junctions.sort_by_key(|coord| (coord.row, coord.col));
+        junctions.dedup();
+        assert!(
+            junctions.len() <= 64,
+            "{} junctions don't fit in a u64 mask",
+            junctions.len()
+        );
+
+        let idx: HashMap<&UCoord, usize> = junctions
+            .iter()
+            .enumerate()
+            .map(|(idx, coord)| (*coord, idx))
+            .collect();
+
+        let mut adjacency: Vec<Vec<(usize, usize)>> = vec![Vec::new(); junctions.len()];
+        for ((from, to), dist) in map.edges.iter() {
+            adjacency[idx[from]].push((idx[to], *dist));
+        }
+        for neighbors in adjacency.iter_mut() {
+            neighbors.sort();
+        }
+
+        let end = idx[&map.end];
+        let into_end: Vec<(usize, usize)> = adjacency
+            .iter()
+            .enumerate()
+            .filter_map(|(junction, neighbors)| {
+                neighbors
+                    .iter()
+                    .find(|(neighbor, _)| *neighbor == end)
+                    .map(|(_, dist)| (junction, *dist))
+            })
+            .collect();
+
+        Self {
+            junctions: junctions.into_iter().cloned().collect(),
+            start: idx[&map.start],
+            end,
+            adjacency,
+            last_before_end: if into_end.len() == 1 {
+                Some(into_end[0])
+            } else {
+                None
+            },
+        }
+    }
+
+    /// The longest path from start to end that visits no junction twice,
+    /// or None if the end can't be reached
+    pub fn longest_hike(&self) -> Option<Hike> {
+        let mut best = Best::default();
+        let mut path: Vec<usize> = vec![self.start];
+        self.search(1 << self.start, 0, &mut path, &mut best);
+        self.to_hike(best)
+    }
+
+    /// `longest_hike`, with the search split across threads. The first few
+    /// levels of the search tree are expanded up front until there is a
+    /// branch for every thread.
+    pub fn longest_hike_parallel(&self) -> Option<Hike> {
+        let n_threads = thread::available_parallelism().map_or(1, |n| n.get());
+
+        let mut best = Best::default();
+        let mut branches: Vec<(Vec<usize>, u64, usize)> =
+            vec![(vec![self.start], 1 << self.start, 0)];
+        while branches.len() < n_threads {
+            let mut next_branches: Vec<(Vec<usize>, u64, usize)> = Vec::new();
+            for (path, visited, dist) in branches.iter() {
+                let junction = *path.last().unwrap();
+                if let Some(final_dist) = self.finish(junction, *dist) {
+                    best.offer(final_dist, &self.finished_path(path));
+                    continue;
+                }
+                for (neighbor, step) in self.adjacency[junction].iter() {
+                    if visited & (1 << neighbor) == 0 {
+                        let mut next_path = path.clone();
+                        next_path.push(*neighbor);
+                        next_branches.push((next_path, visited | (1 << neighbor), dist + step));
+                    }
+                }
+            }
+
+            let exhausted = next_branches.is_empty();
+            branches = next_branches;
+            if exhausted {
+                break;
+            }
+        }
+
+        thread::scope(|scope| {
+            let handles: Vec<_> = (0..n_threads)
+                .map(|thread_idx| {
+                    let branches = &branches;
+                    scope.spawn(move || {
+                        let mut thread_best = Best::default();
+                        for (path, visited, dist) in
+                            branches.iter().skip(thread_idx).step_by(n_threads)
+                        {
+                            let mut path = path.clone();
+                            self.search(*visited, *dist, &mut path, &mut thread_best);
+                        }
+                        thread_best
+                    })
+                })
+                .collect();
+
+            for handle in handles {
+                best.merge(handle.join().unwrap());
+            }
+        });
+
+        self.to_hike(best)
+    }
+
+    /// The total length if a path at `junction` after `dist` has only one
+    /// way left to go: it is at the end, or next to it
+    fn finish(&self, junction: usize, dist: usize) -> Option<usize> {
+        if junction == self.end {
+            return Some(dist);
+        }
+        match self.last_before_end {
+            Some((last, to_end)) if last == junction => Some(dist + to_end),
+            _ => None,
+        }
+    }
+
+    /// `path`, extended to the end if `finish` cut it short
+    fn finished_path(&self, path: &[usize]) -> Vec<usize> {
+        let mut path = path.to_vec();
+        if *path.last().unwrap() != self.end {
+            path.push(self.end);
+        }
+        path
+    }
+
+    fn search(&self, visited: u64, dist: usize, path: &mut Vec<usize>, best: &mut Best) {
+        let junction = *path.last().unwrap();
+        if let Some(final_dist) = self.finish(junction, dist) {
+            if final_dist > best.length || best.path.is_empty() {
+                best.offer(final_dist, &self.finished_path(path));
+            }
+            return;
+        }
+
+        for (neighbor, step) in self.adjacency[junction].iter() {
+            if visited & (1 << neighbor) == 0 {
+                path.push(*neighbor);
+                self.search(visited | (1 << neighbor), dist + step, path, best);
+                path.pop();
+            }
+        }
+    }
+
+    fn to_hike(&self, best: Best) -> Option<Hike> {
+        if best.path.is_empty() {
+            return None;
+        }
+        Some(Hike {
+            length: best.length,
+            junctions: best
+                .path
+                .into_iter()
+                .map(|idx| self.junctions[idx].clone())
+                .collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Day23;
+    use shared::input::AocBufReader;
+
+    #[test]
+    fn test_longest_path() {
+        let map = Map::from_reader(AocBufReader::from_string("inputs/test.txt"), Day23::Part1);
+        let graph = JunctionGraph::new(&map);
+        let hike = graph.longest_hike().unwrap();
+        assert_eq!(hike.length, 94);
+        assert_eq!(hike.junctions.first(), Some(&map.start));
+        assert_eq!(hike.junctions.last(), Some(&map.end));
+        assert_eq!(graph.longest_hike_parallel().unwrap().length, 94);
+
+        let map = Map::from_reader(AocBufReader::from_string("inputs/test.txt"), Day23::Part2);
+        let graph = JunctionGraph::new(&map);
+        assert_eq!(graph.last_before_end.map(|(_, dist)| dist), Some(5));
+        assert_eq!(graph.longest_hike().unwrap().length, 154);
+        let hike = graph.longest_hike_parallel().unwrap();
+        assert_eq!(hike.length, 154);
+        let n_steps: usize = hike
+            .junctions
+            .windows(2)
+            .map(|step| map.edges[&(step[0].clone(), step[1].clone())])
+            .sum();
+        assert_eq!(n_steps, 154);
+    }
+}
@@ -1,9 +1,33 @@
 use std::collections::{HashMap, HashSet};
 
 use shared::coords::UCoord;
+use shared::dot::{Format, Graph};
 use shared::input::AocBufReader;
 
+mod junctions;
+
+use junctions::{Hike, JunctionGraph};
+
 fn main() {
+    if std::env::args().nth(1).as_deref() == Some("--render") {
+        for part in [Day23::Part1, Day23::Part2] {
+            let map = Map::from_reader(AocBufReader::from_string("inputs/part_1.txt"), part);
+            let hike = JunctionGraph::new(&map).longest_hike_parallel().unwrap();
+            println!("{:?}: {}\n{}", part, hike.length, map.render_hike(&hike));
+        }
+        return;
+    }
+
+    if let Some(format) = Format::from_args() {
+        let map = Map::from_reader(AocBufReader::from_string("inputs/part_1.txt"), Day23::Part2);
+        let hike = JunctionGraph::new(&map).longest_hike_parallel().unwrap();
+        let mut graph = map.to_graph();
+        let labels: Vec<String> = hike.junctions.iter().map(junction_label).collect();
+        graph.highlight_path(&labels.iter().map(|l| l.as_str()).collect::<Vec<&str>>());
+        print!("{}", graph.render(format));
+        return;
+    }
+
     let result = part_1(AocBufReader::from_string("inputs/part_1.txt"));
     println!("part 1: {result}");
 
@@ -13,20 +37,30 @@ fn main() {
 
 fn part_1(reader: AocBufReader) -> usize {
     let map = Map::from_reader(reader, Day23::Part1);
-    map.find_longest_path()
+    JunctionGraph::new(&map).longest_hike().unwrap().length
 }
 
 fn part_2(reader: AocBufReader) -> usize {
     let map = Map::from_reader(reader, Day23::Part2);
-    map.find_longest_path()
+    JunctionGraph::new(&map)
+        .longest_hike_parallel()
+        .unwrap()
+        .length
+}
+
+fn junction_label(coord: &UCoord) -> String {
+    format!("{},{}", coord.row, coord.col)
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Day23 {
     Part1,
     Part2,
 }
 
 struct Map {
+    char_array: CharArray,
+    part: Day23,
     start: UCoord,
     end: UCoord,
     edges: HashMap<(UCoord, UCoord), usize>,
@@ -77,39 +111,131 @@ impl Map {
         };
 
         Self {
+            char_array,
+            part,
             start: start.unwrap(),
             end: end.unwrap(),
             edges,
         }
     }
 
-    fn find_longest_path(&self) -> usize {
-        let mut longest_path_length: usize = 0;
-        let mut living_paths: Vec<(HashSet<UCoord>, UCoord, usize)> =
-            vec![(HashSet::from([self.start.clone()]), self.start.clone(), 0)];
-        while let Some((path_coords, tip, current_dist)) = living_paths.pop() {
-            let next_dist: Vec<(UCoord, usize)> = self
-                .edges
-                .iter()
-                .filter(|((from, to), _)| from == &tip && !path_coords.contains(to))
-                .map(|((_, to), dist)| (to.clone(), *dist))
-                .collect();
-
-            for (next, dist) in next_dist {
-                if next == self.end {
-                    let path_lenth = current_dist + dist;
-                    if path_lenth > longest_path_length {
-                        longest_path_length = path_lenth;
-                    }
-                } else {
-                    let mut next_coords = path_coords.clone();
-                    next_coords.insert(next.clone());
-                    living_paths.push((next_coords, next, current_dist + dist));
+    /// Every tile of `hike`, from the start to the end
+    fn route(&self, hike: &Hike) -> Vec<UCoord> {
+        let junctions: HashSet<&UCoord> = self.edges.keys().map(|(from, _)| from).collect();
+        let mut route: Vec<UCoord> = vec![hike.junctions[0].clone()];
+        for step in hike.junctions.windows(2) {
+            route.extend(self.corridor(&step[0], &step[1], &junctions));
+        }
+        route
+    }
+
+    /// The tiles walked going from junction `from` to junction `to`, not
+    /// counting `from`. Tiles between junctions have at most two path
+    /// neighbors, so each way out of `from` is a single corridor.
+    fn corridor(&self, from: &UCoord, to: &UCoord, junctions: &HashSet<&UCoord>) -> Vec<UCoord> {
+        let dist = self.edges[&(from.clone(), to.clone())];
+        for first in self.steps(from) {
+            let mut previous = from.clone();
+            let mut corridor: Vec<UCoord> = vec![first];
+            loop {
+                let current = corridor.last().unwrap().clone();
+                if &current == to && corridor.len() == dist {
+                    return corridor;
+                }
+                if junctions.contains(&current) || current == self.end {
+                    break;
                 }
+
+                let next: Vec<UCoord> = self
+                    .steps(&current)
+                    .into_iter()
+                    .filter(|coord| coord != &previous)
+                    .collect();
+                if next.len() != 1 {
+                    break;
+                }
+                previous = current;
+                corridor.extend(next);
             }
         }
+        panic!("No corridor of length {dist} from {from:?} to {to:?}")
+    }
 
-        longest_path_length
+    /// The tiles one step away from `coord` that may be walked onto
+    fn steps(&self, coord: &UCoord) -> Vec<UCoord> {
+        [
+            (coord.north(), '^'),
+            (coord.east(), '>'),
+            (coord.south(), 'v'),
+            (coord.west(), '<'),
+        ]
+        .into_iter()
+        .filter_map(|(neighbor, slope)| {
+            let neighbor = neighbor?;
+            match (self.char_array.get(&neighbor)?, self.part) {
+                ('#', _) => None,
+                ('.', _) | (_, Day23::Part2) => Some(neighbor),
+                (c, Day23::Part1) if c == slope => Some(neighbor),
+                _ => None,
+            }
+        })
+        .collect()
+    }
+
+    /// The map with every tile of `hike` drawn as `O`
+    fn render_hike(&self, hike: &Hike) -> String {
+        let mut chars = self.char_array.chars.clone();
+        for coord in self.route(hike) {
+            chars[coord.row][coord.col] = 'O';
+        }
+        chars
+            .into_iter()
+            .map(|row| row.into_iter().collect::<String>())
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    /// The compressed junction graph with path lengths as edge labels.
+    /// If every edge can be walked both ways (part 2) the graph is drawn
+    /// undirected, with one edge per pair of junctions.
+    fn to_graph(&self) -> Graph {
+        let undirected = self
+            .edges
+            .iter()
+            .all(|((from, to), dist)| self.edges.get(&(to.clone(), from.clone())) == Some(dist));
+
+        let mut junctions: Vec<&UCoord> = self.edges.keys().map(|(from, _)| from).collect();
+        junctions.sort_by_key(|coord| (coord.row, coord.col));
+        junctions.dedup();
+
+        let mut graph = if undirected {
+            Graph::undirected()
+        } else {
+            Graph::directed()
+        };
+        graph.add_node(&junction_label(&self.start), Some("start"));
+        for junction in junctions.iter() {
+            if *junction != &self.start {
+                graph.add_node(&junction_label(junction), Some("junction"));
+            }
+        }
+        graph.add_node(&junction_label(&self.end), Some("end"));
+
+        let mut edges: Vec<(&(UCoord, UCoord), &usize)> = self
+            .edges
+            .iter()
+            .filter(|((from, to), _)| !undirected || (from.row, from.col) < (to.row, to.col))
+            .collect();
+        edges.sort_by_key(|((from, to), _)| (from.row, from.col, to.row, to.col));
+        for ((from, to), dist) in edges {
+            graph.add_edge(
+                &junction_label(from),
+                &junction_label(to),
+                Some(dist.to_string()),
+            );
+        }
+
+        graph
     }
 
     fn find_edges_part_1(
@@ -131,54 +257,50 @@ impl Map {
 
                 if let Some(north) = current_node.north() {
                     match char_array.get(&north) {
-                        None => (),
-                        Some(c) => {
+                        Some(c)
                             if (c == '.' || c == '^')
                                 && !visited.contains(&north)
-                                && !current_to_visit_coords.contains(&north)
-                            {
-                                to_visit.push((north, dist + 1));
-                            }
+                                && !current_to_visit_coords.contains(&north) =>
+                        {
+                            to_visit.push((north, dist + 1));
                         }
+                        _ => (),
                     }
                 }
                 if let Some(east) = current_node.east() {
                     match char_array.get(&east) {
-                        None => (),
-                        Some(c) => {
+                        Some(c)
                             if (c == '.' || c == '>')
                                 && !visited.contains(&east)
-                                && !current_to_visit_coords.contains(&east)
-                            {
-                                to_visit.push((east, dist + 1));
-                            }
+                                && !current_to_visit_coords.contains(&east) =>
+                        {
+                            to_visit.push((east, dist + 1));
                         }
+                        _ => (),
                     }
                 }
                 if let Some(south) = current_node.south() {
                     match char_array.get(&south) {
-                        None => (),
-                        Some(c) => {
+                        Some(c)
                             if (c == '.' || c == 'v')
                                 && !visited.contains(&south)
-                                && !current_to_visit_coords.contains(&south)
-                            {
-                                to_visit.push((south, dist + 1));
-                            }
+                                && !current_to_visit_coords.contains(&south) =>
+                        {
+                            to_visit.push((south, dist + 1));
                         }
+                        _ => (),
                     }
                 }
                 if let Some(west) = current_node.west() {
                     match char_array.get(&west) {
-                        None => (),
-                        Some(c) => {
+                        Some(c)
                             if (c == '.' || c == '<')
                                 && !visited.contains(&west)
-                                && !current_to_visit_coords.contains(&west)
-                            {
-                                to_visit.push((west, dist + 1));
-                            }
+                                && !current_to_visit_coords.contains(&west) =>
+                        {
+                            to_visit.push((west, dist + 1));
                         }
+                        _ => (),
                     }
                 }
             }
@@ -205,54 +327,50 @@ impl Map {
 
                 if let Some(north) = current_node.north() {
                     match char_array.get(&north) {
-                        None => (),
-                        Some(c) => {
+                        Some(c)
                             if c != '#'
                                 && !visited.contains(&north)
-                                && !current_to_visit_coords.contains(&north)
-                            {
-                                to_visit.push((north, dist + 1));
-                            }
+                                && !current_to_visit_coords.contains(&north) =>
+                        {
+                            to_visit.push((north, dist + 1));
                         }
+                        _ => (),
                     }
                 }
                 if let Some(east) = current_node.east() {
                     match char_array.get(&east) {
-                        None => (),
-                        Some(c) => {
+                        Some(c)
                             if c != '#'
                                 && !visited.contains(&east)
-                                && !current_to_visit_coords.contains(&east)
-                            {
-                                to_visit.push((east, dist + 1));
-                            }
+                                && !current_to_visit_coords.contains(&east) =>
+                        {
+                            to_visit.push((east, dist + 1));
                         }
+                        _ => (),
                     }
                 }
                 if let Some(south) = current_node.south() {
                     match char_array.get(&south) {
-                        None => (),
-                        Some(c) => {
+                        Some(c)
                             if c != '#'
                                 && !visited.contains(&south)
-                                && !current_to_visit_coords.contains(&south)
-                            {
-                                to_visit.push((south, dist + 1));
-                            }
+                                && !current_to_visit_coords.contains(&south) =>
+                        {
+                            to_visit.push((south, dist + 1));
                         }
+                        _ => (),
                     }
                 }
                 if let Some(west) = current_node.west() {
                     match char_array.get(&west) {
-                        None => (),
-                        Some(c) => {
+                        Some(c)
                             if c != '#'
                                 && !visited.contains(&west)
-                                && !current_to_visit_coords.contains(&west)
-                            {
-                                to_visit.push((west, dist + 1));
-                            }
+                                && !current_to_visit_coords.contains(&west) =>
+                        {
+                            to_visit.push((west, dist + 1));
                         }
+                        _ => (),
                     }
                 }
             }
@@ -316,3 +434,50 @@ impl CharArray {
         coord.row < self.n_rows && coord.col < self.n_cols
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_graph() {
+        let map = Map::from_reader(AocBufReader::from_string("inputs/test.txt"), Day23::Part1);
+        let graph = map.to_graph();
+        assert_eq!(graph.nodes().len(), 9);
+        assert_eq!(graph.nodes()[0].id, "0,1");
+        assert_eq!(graph.nodes()[8].kind.as_deref(), Some("end"));
+        assert_eq!(graph.edges().len(), map.edges.len());
+        assert!(graph.to_dot().starts_with("digraph"));
+
+        let map = Map::from_reader(AocBufReader::from_string("inputs/test.txt"), Day23::Part2);
+        let graph = map.to_graph();
+        assert_eq!(graph.edges().len() * 2, map.edges.len());
+        assert!(graph.to_dot().starts_with("graph"));
+        assert!(graph.edges().iter().any(|edge| edge.from == "0,1"
+            && edge.to == "5,3"
+            && edge.label.as_deref() == Some("15")));
+    }
+    #[test]
+    fn test_route() {
+        for (part, length) in [(Day23::Part1, 94), (Day23::Part2, 154)] {
+            let map = Map::from_reader(AocBufReader::from_string("inputs/test.txt"), part);
+            let hike = JunctionGraph::new(&map).longest_hike().unwrap();
+            let route = map.route(&hike);
+
+            assert_eq!(route.len(), length + 1);
+            assert_eq!(route.first(), Some(&map.start));
+            assert_eq!(route.last(), Some(&map.end));
+            assert_eq!(
+                route.iter().collect::<HashSet<&UCoord>>().len(),
+                route.len()
+            );
+            for step in route.windows(2) {
+                assert!(map.steps(&step[0]).contains(&step[1]));
+            }
+
+            let rendered = map.render_hike(&hike);
+            assert_eq!(rendered.matches('O').count(), length + 1);
+            assert!(rendered.starts_with("#O#####################\n#OOOOOOO#########"));
+        }
+    }
+}
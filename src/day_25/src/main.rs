@@ -1,18 +1,32 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 use std::mem;
 
+use shared::dot::{self, Format};
 use shared::input::AocBufReader;
 
 use rand::{rngs::ThreadRng, Rng};
 
 fn main() {
+    if let Some(format) = Format::from_args() {
+        let graph = Graph::from_reader(AocBufReader::from_string("inputs/part_1.txt"));
+        let cut = minimum_cut(&graph, false);
+        print!("{}", graph.to_dot_graph(Some(&cut)).render(format));
+        return;
+    }
+
     let result = part_1(AocBufReader::from_string("inputs/part_1.txt"));
     println!("part 1: {result}");
 }
 
 fn part_1(reader: AocBufReader) -> usize {
     let graph = Graph::from_reader(reader);
+    minimum_cut(&graph, true).node_sizes.values().product()
+}
 
+/// Contract copies of `graph` until one is left with the known minimum cut
+/// of 3. With `report_progress`, print every new smallest cut found; the
+/// graph export turns it off so that stdout is just the graph.
+fn minimum_cut(graph: &Graph, report_progress: bool) -> Graph {
     let mut minimum_cut: usize = usize::MAX;
     let mut copy: Graph;
     loop {
@@ -21,7 +35,9 @@ fn part_1(reader: AocBufReader) -> usize {
 
         if copy.edges.len() < minimum_cut {
             minimum_cut = copy.edges.len();
-            println!("found new minimum cut {}", minimum_cut);
+            if report_progress {
+                println!("found new minimum cut {}", minimum_cut);
+            }
         }
 
         if copy.edges.len() == 3 {
@@ -29,7 +45,7 @@ fn part_1(reader: AocBufReader) -> usize {
             break;
         }
     }
-    copy.node_sizes.values().fold(1usize, |acc, x| acc * x)
+    copy
 }
 
 #[derive(Clone)]
@@ -73,6 +89,38 @@ impl Graph {
         }
     }
 
+    /// The components and wires of `self`. If `contracted` is `self` reduced
+    /// to two nodes by `find_cut`, nodes are drawn by which side of the cut
+    /// they fall on and the wires that cross the cut are highlighted.
+    fn to_dot_graph(&self, contracted: Option<&Graph>) -> dot::Graph {
+        let mut sides: HashMap<&str, String> = HashMap::new();
+        if let Some(contracted) = contracted {
+            let mut contracted_nodes: Vec<&String> = contracted.node_sizes.keys().collect();
+            contracted_nodes.sort();
+            for (side_idx, contracted_node) in contracted_nodes.into_iter().enumerate() {
+                for node in contracted_node.split('-') {
+                    sides.insert(node, format!("side {}", side_idx + 1));
+                }
+            }
+        }
+
+        let mut nodes: Vec<&String> = self.node_sizes.keys().collect();
+        nodes.sort();
+
+        let mut graph = dot::Graph::undirected();
+        for node in nodes {
+            graph.add_node(node, sides.get(node.as_str()).map(|side| side.as_str()));
+        }
+        for (from, to) in self.edges.iter() {
+            let edge_idx = graph.add_edge(from, to, None);
+            if contracted.is_some() && sides[from.as_str()] != sides[to.as_str()] {
+                graph.highlight_edge(edge_idx);
+            }
+        }
+
+        graph
+    }
+
     /// We've been given a known minimum cut value (3) and are tasked
     /// with finding the partition of the vertices that achieves this cut.
     /// This is a min-cut problem and we'll use Karger's algorithm, which
@@ -133,3 +181,38 @@ impl Graph {
         self.edges.get(idx).unwrap()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_dot_graph() {
+        let graph = Graph::from_reader(AocBufReader::from_string("inputs/test.txt"));
+        let plain = graph.to_dot_graph(None);
+        assert_eq!(plain.nodes().len(), 15);
+        assert_eq!(plain.edges().len(), 33);
+        assert!(plain.nodes().iter().all(|node| node.kind.is_none()));
+
+        let cut = minimum_cut(&graph, false);
+        let highlighted = graph.to_dot_graph(Some(&cut));
+        let mut cut_edges: Vec<(&str, &str)> = highlighted
+            .edges()
+            .iter()
+            .filter(|edge| edge.highlighted)
+            .map(|edge| (edge.from.as_str(), edge.to.as_str()))
+            .collect();
+        cut_edges.sort();
+        assert_eq!(
+            cut_edges,
+            vec![("bvb", "cmg"), ("hfx", "pzl"), ("jqt", "nvd")]
+        );
+
+        let n_side_1 = highlighted
+            .nodes()
+            .iter()
+            .filter(|node| node.kind.as_deref() == Some("side 1"))
+            .count();
+        assert!(n_side_1 == 6 || n_side_1 == 9);
+    }
+}
@@ -1,5 +1,6 @@
 use std::collections::{HashMap, HashSet};
 
+use shared::dot::{Format, Graph};
 use shared::input::AocBufReader;
 
 use num::integer;
@@ -10,6 +11,12 @@ static LINE_REGEX: Lazy<Regex> =
     Lazy::new(|| Regex::new(r"^(?<start>.{3}) = \((?<left>.{3}), (?<right>.{3})\)$").unwrap());
 
 fn main() {
+    if let Some(format) = Format::from_args() {
+        let graph = map_graph(AocBufReader::from_string("inputs/part_1.txt"));
+        print!("{}", graph.render(format));
+        return;
+    }
+
     let result = part_1(AocBufReader::from_string("inputs/part_1.txt"));
     println!("part 1: {result}");
 
@@ -19,15 +26,30 @@ fn main() {
 
 fn part_1(reader: AocBufReader) -> usize {
     let (mut instruction_generator, map) = parse_input(reader);
-    let mut current_position: String = "AAA".to_string();
-    let mut n_steps: usize = 0;
+    part_1_route(&mut instruction_generator, &map).len() - 1
+}
 
-    while current_position != "ZZZ".to_string() {
-        current_position = map.step(&current_position, &instruction_generator.next());
-        n_steps += 1;
+/// Every node visited walking from AAA to ZZZ, including both ends
+fn part_1_route(instruction_generator: &mut InstructionGenerator, map: &Map) -> Vec<String> {
+    let mut route: Vec<String> = vec!["AAA".to_string()];
+    while route.last().unwrap() != "ZZZ" {
+        let next = map.step(route.last().unwrap(), &instruction_generator.next());
+        route.push(next);
     }
 
-    n_steps
+    route
+}
+
+/// The node map, with the part 1 route highlighted if the map has one
+fn map_graph(reader: AocBufReader) -> Graph {
+    let (mut instruction_generator, map) = parse_input(reader);
+    let mut graph = map.to_graph();
+    if map.mapping.contains_key("AAA") {
+        let route = part_1_route(&mut instruction_generator, &map);
+        graph.highlight_path(&route.iter().map(|n| n.as_str()).collect::<Vec<&str>>());
+    }
+
+    graph
 }
 
 /// It turns out the puzzle constructor is much nicer than the instruction indicate!
@@ -145,6 +167,35 @@ impl Map {
             .cloned()
             .collect()
     }
+
+    /// Starting nodes and end nodes are drawn as their own kinds; nodes
+    /// whose left and right are the same get a single "L/R" edge
+    fn to_graph(&self) -> Graph {
+        let mut nodes: Vec<&String> = self.mapping.keys().collect();
+        nodes.sort();
+
+        let mut graph = Graph::directed();
+        for node in nodes.iter() {
+            if node.ends_with('A') {
+                graph.add_node(node, Some("start"));
+            } else if is_end_node(node) {
+                graph.add_node(node, Some("end"));
+            } else {
+                graph.add_node(node, None);
+            }
+        }
+        for node in nodes {
+            let (left, right) = self.mapping.get(node).unwrap();
+            if left == right {
+                graph.add_edge(node, left, Some("L/R".to_string()));
+            } else {
+                graph.add_edge(node, left, Some("L".to_string()));
+                graph.add_edge(node, right, Some("R".to_string()));
+            }
+        }
+
+        graph
+    }
 }
 
 fn map_cycle(
@@ -244,4 +295,32 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn test_map_graph() {
+        let graph = map_graph(AocBufReader::from_string("inputs/test.txt"));
+        let edges: Vec<(&str, &str, &str, bool)> = graph
+            .edges()
+            .iter()
+            .map(|e| {
+                (
+                    e.from.as_str(),
+                    e.to.as_str(),
+                    e.label.as_deref().unwrap(),
+                    e.highlighted,
+                )
+            })
+            .collect();
+        assert_eq!(
+            edges,
+            vec![
+                ("AAA", "BBB", "L/R", true),
+                ("BBB", "AAA", "L", true),
+                ("BBB", "ZZZ", "R", true),
+                ("ZZZ", "ZZZ", "L/R", false),
+            ]
+        );
+        assert_eq!(graph.nodes()[0].kind.as_deref(), Some("start"));
+        assert_eq!(graph.nodes()[2].kind.as_deref(), Some("end"));
+    }
 }
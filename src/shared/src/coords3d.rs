@@ -1,3 +1,7 @@
+use std::ops::{Add, Mul, Sub};
+
+use crate::hyperbox::HyperBox;
+
 #[derive(PartialEq, Eq, Hash, Clone, Debug)]
 pub struct U3Coord {
     pub x: usize,
@@ -95,3 +99,121 @@ impl S3Coord {
         Self::new(self.x + 1, self.y, self.z)
     }
 }
+
+/// A box of voxels with _inclusive_ bounds on x, y and z (axes 0, 1 and 2).
+/// Volume, intersection, containment and iterating the voxels with
+/// `points` all come from `HyperBox`.
+pub type Cuboid<T> = HyperBox<T, 3>;
+
+impl<T> Cuboid<T>
+where
+    T: Ord + Copy + From<u8> + Add<Output = T> + Sub<Output = T> + Mul<Output = T>,
+{
+    /// The cuboid moved by `offset`
+    pub fn translate(&self, offset: [T; 3]) -> Self {
+        Self {
+            min: [0, 1, 2].map(|axis| self.min[axis] + offset[axis]),
+            max: [0, 1, 2].map(|axis| self.max[axis] + offset[axis]),
+        }
+    }
+
+    /// The cuboid moved along `axis` so that it starts at `min`,
+    /// which also works for unsigned `T` when moving down
+    pub fn moved_to(&self, axis: usize, min: T) -> Self {
+        let mut result = self.clone();
+        result.max[axis] = min + (self.max[axis] - self.min[axis]);
+        result.min[axis] = min;
+        result
+    }
+
+    /// The shadow the cuboid casts along `axis`, in the plane of the other
+    /// two axes, e.g. `project(2)` is the (x, y) footprint
+    pub fn project(&self, axis: usize) -> HyperBox<T, 2> {
+        let [a, b] = match axis {
+            0 => [1, 2],
+            1 => [0, 2],
+            2 => [0, 1],
+            _ => panic!("Cuboids have no axis {axis}"),
+        };
+        HyperBox {
+            min: [self.min[a], self.min[b]],
+            max: [self.max[a], self.max[b]],
+        }
+    }
+
+    /// Is `other` stacked directly on top of `self` along `axis`, sharing
+    /// part of a face?
+    pub fn touches_below(&self, other: &Self, axis: usize) -> bool {
+        self.max[axis] + T::from(1) == other.min[axis]
+            && self
+                .project(axis)
+                .intersection(&other.project(axis))
+                .is_some()
+    }
+
+    /// Do the cuboids share part of a face, on any axis?
+    pub fn face_adjacent(&self, other: &Self) -> bool {
+        (0..3).any(|axis| self.touches_below(other, axis) || other.touches_below(self, axis))
+    }
+}
+
+impl Cuboid<usize> {
+    pub fn voxels(&self) -> impl Iterator<Item = U3Coord> {
+        self.points().map(|[x, y, z]| U3Coord::new(x, y, z))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cuboid(min: [i64; 3], max: [i64; 3]) -> Cuboid<i64> {
+        Cuboid::new(min, max).unwrap()
+    }
+
+    #[test]
+    fn test_translate() {
+        let brick = cuboid([0, 0, 5], [2, 0, 5]);
+        assert_eq!(brick.translate([1, -1, 2]), cuboid([1, -1, 7], [3, -1, 7]));
+
+        let tower: Cuboid<usize> = Cuboid::new([1, 1, 8], [1, 1, 9]).unwrap();
+        assert_eq!(
+            tower.moved_to(2, 1),
+            Cuboid::new([1, 1, 1], [1, 1, 2]).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_project() {
+        let brick = cuboid([0, 1, 2], [3, 4, 5]);
+        assert_eq!(brick.project(0), HyperBox::new([1, 2], [4, 5]).unwrap());
+        assert_eq!(brick.project(1), HyperBox::new([0, 2], [3, 5]).unwrap());
+        assert_eq!(brick.project(2), HyperBox::new([0, 1], [3, 4]).unwrap());
+    }
+
+    #[test]
+    fn test_adjacency() {
+        let bottom = cuboid([1, 0, 1], [1, 2, 1]);
+        let across = cuboid([0, 0, 2], [2, 0, 2]);
+        let apart = cuboid([0, 2, 3], [2, 2, 3]);
+        assert!(bottom.touches_below(&across, 2));
+        assert!(!across.touches_below(&bottom, 2));
+        assert!(!bottom.touches_below(&apart, 2));
+        assert!(bottom.face_adjacent(&across) && across.face_adjacent(&bottom));
+
+        // touching along an edge only is not a shared face
+        let diagonal = cuboid([2, 3, 1], [2, 3, 1]);
+        assert!(!bottom.face_adjacent(&diagonal));
+        assert!(cuboid([2, 0, 1], [2, 0, 1]).face_adjacent(&bottom));
+    }
+
+    #[test]
+    fn test_voxels() {
+        let brick: Cuboid<usize> = Cuboid::new([1, 1, 8], [1, 1, 9]).unwrap();
+        assert_eq!(
+            brick.voxels().collect::<Vec<U3Coord>>(),
+            vec![U3Coord::new(1, 1, 8), U3Coord::new(1, 1, 9)]
+        );
+        assert_eq!(brick.volume(), 2);
+    }
+}
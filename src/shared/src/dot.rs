@@ -0,0 +1,444 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+/// Shapes and fill colors handed out to node kinds in the order the kinds
+/// first appear: (DOT shape, Mermaid opening bracket, Mermaid closing bracket, fill)
+const KIND_STYLES: [KindStyle; 6] = [
+    ("box", "[", "]", "lightblue"),
+    ("diamond", "{", "}", "palegreen"),
+    ("hexagon", "{{", "}}", "khaki"),
+    ("circle", "((", "))", "lightpink"),
+    ("parallelogram", "[/", "/]", "plum"),
+    ("trapezium", "[/", "\\]", "lightgrey"),
+];
+
+type KindStyle = (&'static str, &'static str, &'static str, &'static str);
+
+const HIGHLIGHT_COLOR: &str = "red";
+
+/// Output formats understood by `Graph::render`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Dot,
+    Mermaid,
+}
+
+impl Format {
+    /// The format requested on the command line with `--dot` or `--mermaid`,
+    /// or None if the first argument is anything else, so a program can
+    /// handle its own flags too
+    pub fn from_args() -> Option<Self> {
+        Self::from_arg_list(&std::env::args().skip(1).collect::<Vec<String>>())
+    }
+
+    fn from_arg_list(args: &[String]) -> Option<Self> {
+        let format = match args.first().map(|arg| arg.as_str()) {
+            Some("--dot") => Self::Dot,
+            Some("--mermaid") => Self::Mermaid,
+            _ => return None,
+        };
+        if let Some(arg) = args.get(1) {
+            panic!("Unknown argument {arg} after {}", args[0]);
+        }
+        Some(format)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Node {
+    pub id: String,
+    pub kind: Option<String>,
+    pub highlighted: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Edge {
+    pub from: String,
+    pub to: String,
+    pub label: Option<String>,
+    pub highlighted: bool,
+}
+
+/// A graph to be written out as Graphviz DOT or a Mermaid flowchart.
+/// Nodes and edges are written in the order they were added.
+#[derive(Debug, Clone)]
+pub struct Graph {
+    directed: bool,
+    nodes: Vec<Node>,
+    node_idx: HashMap<String, usize>,
+    edges: Vec<Edge>,
+}
+
+impl Graph {
+    pub fn directed() -> Self {
+        Self::new(true)
+    }
+
+    pub fn undirected() -> Self {
+        Self::new(false)
+    }
+
+    fn new(directed: bool) -> Self {
+        Self {
+            directed,
+            nodes: Vec::new(),
+            node_idx: HashMap::new(),
+            edges: Vec::new(),
+        }
+    }
+
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    pub fn edges(&self) -> &[Edge] {
+        &self.edges
+    }
+
+    /// Add a node, or set the kind of an existing one. Nodes of the same
+    /// kind share a shape and fill color.
+    pub fn add_node(&mut self, id: &str, kind: Option<&str>) {
+        let idx = self.node(id);
+        if kind.is_some() {
+            self.nodes[idx].kind = kind.map(|k| k.to_string());
+        }
+    }
+
+    /// Add an edge, adding its endpoints as plain nodes if needed,
+    /// and return its index for `highlight_edge`
+    pub fn add_edge(&mut self, from: &str, to: &str, label: Option<String>) -> usize {
+        self.node(from);
+        self.node(to);
+        self.edges.push(Edge {
+            from: from.to_string(),
+            to: to.to_string(),
+            label,
+            highlighted: false,
+        });
+        self.edges.len() - 1
+    }
+
+    pub fn highlight_node(&mut self, id: &str) {
+        let idx = self.node(id);
+        self.nodes[idx].highlighted = true;
+    }
+
+    /// Highlight an edge and both of its endpoints
+    pub fn highlight_edge(&mut self, edge_idx: usize) {
+        self.edges[edge_idx].highlighted = true;
+        let (from, to) = (
+            self.edges[edge_idx].from.clone(),
+            self.edges[edge_idx].to.clone(),
+        );
+        self.highlight_node(&from);
+        self.highlight_node(&to);
+    }
+
+    /// Highlight every node of `path` and, between each consecutive pair,
+    /// the first edge joining them. Panics if there is no such edge.
+    pub fn highlight_path(&mut self, path: &[&str]) {
+        for node in path {
+            self.highlight_node(node);
+        }
+        for step in path.windows(2) {
+            let edge_idx = self
+                .edges
+                .iter()
+                .position(|edge| {
+                    (edge.from == step[0] && edge.to == step[1])
+                        || (!self.directed && edge.from == step[1] && edge.to == step[0])
+                })
+                .unwrap_or_else(|| panic!("No edge from {} to {}", step[0], step[1]));
+            self.edges[edge_idx].highlighted = true;
+        }
+    }
+
+    pub fn render(&self, format: Format) -> String {
+        match format {
+            Format::Dot => self.to_dot(),
+            Format::Mermaid => self.to_mermaid(),
+        }
+    }
+
+    pub fn to_dot(&self) -> String {
+        let kind_styles = self.kind_styles();
+        let (keyword, arrow) = if self.directed {
+            ("digraph", "->")
+        } else {
+            ("graph", "--")
+        };
+
+        let mut result = format!("{keyword} {{\n");
+        for node in self.nodes.iter() {
+            let mut attributes: Vec<String> = Vec::new();
+            if let Some(kind) = &node.kind {
+                let (shape, _, _, fill) = kind_styles[kind.as_str()];
+                attributes.push(format!("shape={shape}"));
+                attributes.push(format!("style=filled, fillcolor={fill}"));
+                attributes.push(format!("tooltip={}", dot_quote(kind)));
+            }
+            if node.highlighted {
+                attributes.push(format!("color={HIGHLIGHT_COLOR}, penwidth=2"));
+            }
+            write!(result, "    {}", dot_quote(&node.id)).unwrap();
+            if !attributes.is_empty() {
+                write!(result, " [{}]", attributes.join(", ")).unwrap();
+            }
+            result.push_str(";\n");
+        }
+
+        for edge in self.edges.iter() {
+            let mut attributes: Vec<String> = Vec::new();
+            if let Some(label) = &edge.label {
+                attributes.push(format!("label={}", dot_quote(label)));
+            }
+            if edge.highlighted {
+                attributes.push(format!("color={HIGHLIGHT_COLOR}, penwidth=2"));
+            }
+            write!(
+                result,
+                "    {} {arrow} {}",
+                dot_quote(&edge.from),
+                dot_quote(&edge.to)
+            )
+            .unwrap();
+            if !attributes.is_empty() {
+                write!(result, " [{}]", attributes.join(", ")).unwrap();
+            }
+            result.push_str(";\n");
+        }
+        result.push_str("}\n");
+        result
+    }
+
+    /// Mermaid ids are restricted, so nodes are written as `n0`, `n1`, ...
+    /// with their id as the label
+    pub fn to_mermaid(&self) -> String {
+        let kind_styles = self.kind_styles();
+        let arrow = if self.directed { "-->" } else { "---" };
+
+        let mut result = "flowchart LR\n".to_string();
+        for (idx, node) in self.nodes.iter().enumerate() {
+            let (open, close) = match &node.kind {
+                Some(kind) => {
+                    let (_, open, close, _) = kind_styles[kind.as_str()];
+                    (open, close)
+                }
+                None => ("[", "]"),
+            };
+            writeln!(
+                result,
+                "    n{idx}{open}\"{}\"{close}",
+                mermaid_escape(&node.id)
+            )
+            .unwrap();
+        }
+
+        for edge in self.edges.iter() {
+            let from = self.node_idx[&edge.from];
+            let to = self.node_idx[&edge.to];
+            match &edge.label {
+                Some(label) => writeln!(
+                    result,
+                    "    n{from} {arrow}|\"{}\"| n{to}",
+                    mermaid_escape(label)
+                ),
+                None => writeln!(result, "    n{from} {arrow} n{to}"),
+            }
+            .unwrap();
+        }
+
+        for (kind, (_, _, _, fill)) in self.kinds().into_iter().zip(KIND_STYLES.iter().cycle()) {
+            let class = mermaid_class(kind);
+            writeln!(result, "    classDef {class} fill:{fill}").unwrap();
+            let members: Vec<String> = self
+                .nodes
+                .iter()
+                .enumerate()
+                .filter(|(_, node)| node.kind.as_deref() == Some(kind))
+                .map(|(idx, _)| format!("n{idx}"))
+                .collect();
+            writeln!(result, "    class {} {class}", members.join(",")).unwrap();
+        }
+
+        for (idx, node) in self.nodes.iter().enumerate() {
+            if node.highlighted {
+                writeln!(
+                    result,
+                    "    style n{idx} stroke:{HIGHLIGHT_COLOR},stroke-width:2px"
+                )
+                .unwrap();
+            }
+        }
+        let highlighted_edges: Vec<String> = self
+            .edges
+            .iter()
+            .enumerate()
+            .filter(|(_, edge)| edge.highlighted)
+            .map(|(idx, _)| idx.to_string())
+            .collect();
+        if !highlighted_edges.is_empty() {
+            writeln!(
+                result,
+                "    linkStyle {} stroke:{HIGHLIGHT_COLOR},stroke-width:2px",
+                highlighted_edges.join(",")
+            )
+            .unwrap();
+        }
+        result
+    }
+
+    fn node(&mut self, id: &str) -> usize {
+        if let Some(idx) = self.node_idx.get(id) {
+            return *idx;
+        }
+        self.nodes.push(Node {
+            id: id.to_string(),
+            kind: None,
+            highlighted: false,
+        });
+        self.node_idx.insert(id.to_string(), self.nodes.len() - 1);
+        self.nodes.len() - 1
+    }
+
+    /// Every distinct node kind, in order of first appearance
+    fn kinds(&self) -> Vec<&str> {
+        let mut kinds: Vec<&str> = Vec::new();
+        for kind in self.nodes.iter().filter_map(|node| node.kind.as_deref()) {
+            if !kinds.contains(&kind) {
+                kinds.push(kind);
+            }
+        }
+        kinds
+    }
+
+    fn kind_styles(&self) -> HashMap<&str, KindStyle> {
+        self.kinds()
+            .into_iter()
+            .zip(KIND_STYLES.iter().cycle().cloned())
+            .collect()
+    }
+}
+
+fn dot_quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn mermaid_escape(s: &str) -> String {
+    s.replace('"', "#quot;")
+}
+
+/// Kinds are prefixed so that one named like a Mermaid keyword, such as
+/// `end`, can't end the diagram early
+fn mermaid_class(kind: &str) -> String {
+    let name: String = kind
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    format!("kind_{name}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example() -> Graph {
+        let mut graph = Graph::directed();
+        graph.add_node("broadcaster", Some("broadcaster"));
+        graph.add_node("a", Some("flip-flop"));
+        graph.add_node("inv", Some("conjunction"));
+        graph.add_edge("broadcaster", "a", None);
+        graph.add_edge("a", "inv", Some("3".to_string()));
+        graph.add_edge("inv", "out", None);
+        graph.highlight_path(&["broadcaster", "a"]);
+        graph
+    }
+
+    #[test]
+    fn test_to_dot() {
+        assert_eq!(
+            example().to_dot(),
+            "digraph {\n\
+             \x20   \"broadcaster\" [shape=box, style=filled, fillcolor=lightblue, tooltip=\"broadcaster\", color=red, penwidth=2];\n\
+             \x20   \"a\" [shape=diamond, style=filled, fillcolor=palegreen, tooltip=\"flip-flop\", color=red, penwidth=2];\n\
+             \x20   \"inv\" [shape=hexagon, style=filled, fillcolor=khaki, tooltip=\"conjunction\"];\n\
+             \x20   \"out\";\n\
+             \x20   \"broadcaster\" -> \"a\" [color=red, penwidth=2];\n\
+             \x20   \"a\" -> \"inv\" [label=\"3\"];\n\
+             \x20   \"inv\" -> \"out\";\n\
+             }\n"
+        );
+    }
+
+    #[test]
+    fn test_to_mermaid() {
+        assert_eq!(
+            example().to_mermaid(),
+            "flowchart LR\n\
+             \x20   n0[\"broadcaster\"]\n\
+             \x20   n1{\"a\"}\n\
+             \x20   n2{{\"inv\"}}\n\
+             \x20   n3[\"out\"]\n\
+             \x20   n0 --> n1\n\
+             \x20   n1 -->|\"3\"| n2\n\
+             \x20   n2 --> n3\n\
+             \x20   classDef kind_broadcaster fill:lightblue\n\
+             \x20   class n0 kind_broadcaster\n\
+             \x20   classDef kind_flip_flop fill:palegreen\n\
+             \x20   class n1 kind_flip_flop\n\
+             \x20   classDef kind_conjunction fill:khaki\n\
+             \x20   class n2 kind_conjunction\n\
+             \x20   style n0 stroke:red,stroke-width:2px\n\
+             \x20   style n1 stroke:red,stroke-width:2px\n\
+             \x20   linkStyle 0 stroke:red,stroke-width:2px\n"
+        );
+    }
+
+    #[test]
+    fn test_undirected_and_escaping() {
+        let mut graph = Graph::undirected();
+        let edge = graph.add_edge("say \"hi\"", "b", Some("7".to_string()));
+        graph.highlight_path(&["b", "say \"hi\""]);
+        assert!(graph.edges()[edge].highlighted);
+        assert!(graph.nodes().iter().all(|node| node.highlighted));
+
+        let dot = graph.to_dot();
+        assert!(dot.starts_with("graph {\n"));
+        assert!(dot.contains("\"say \\\"hi\\\"\" -- \"b\" [label=\"7\", color=red, penwidth=2];"));
+        assert!(graph
+            .to_mermaid()
+            .contains("n0[\"say #quot;hi#quot;\"]\n    n1[\"b\"]\n    n0 ---|\"7\"| n1\n"));
+    }
+
+    #[test]
+    fn test_mermaid_reserved_kind() {
+        let mut graph = Graph::directed();
+        graph.add_node("AAA", Some("start"));
+        graph.add_node("ZZZ", Some("end"));
+        graph.add_edge("AAA", "ZZZ", None);
+        let mermaid = graph.to_mermaid();
+        assert!(mermaid.contains("    classDef kind_end fill:"));
+        assert!(mermaid.contains("    class n1 kind_end\n"));
+        assert!(!mermaid
+            .lines()
+            .any(|line| line.trim() == "end" || line.trim().starts_with("classDef end")));
+    }
+
+    #[test]
+    fn test_format_from_args() {
+        let args = |list: &[&str]| list.iter().map(|a| a.to_string()).collect::<Vec<String>>();
+        assert_eq!(Format::from_arg_list(&args(&["--dot"])), Some(Format::Dot));
+        assert_eq!(
+            Format::from_arg_list(&args(&["--mermaid"])),
+            Some(Format::Mermaid)
+        );
+        assert_eq!(Format::from_arg_list(&args(&[])), None);
+        assert_eq!(Format::from_arg_list(&args(&["--query", "x>5"])), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Unknown argument svg after --dot")]
+    fn test_format_from_args_unknown_value() {
+        Format::from_arg_list(&["--dot".to_string(), "svg".to_string()]);
+    }
+}
@@ -0,0 +1,228 @@
+use std::ops::{Add, Mul, Sub};
+
+/// An axis-aligned box in N dimensions with _inclusive_ bounds,
+/// i.e. `min[axis] <= value <= max[axis]` on every axis
+#[derive(PartialEq, Eq, Hash, Clone, Debug)]
+pub struct HyperBox<T, const N: usize> {
+    pub min: [T; N],
+    pub max: [T; N],
+}
+
+impl<T, const N: usize> HyperBox<T, N>
+where
+    T: Ord + Copy + From<u8> + Add<Output = T> + Sub<Output = T> + Mul<Output = T>,
+{
+    /// None if `min > max` on any axis
+    pub fn new(min: [T; N], max: [T; N]) -> Option<Self> {
+        if min.iter().zip(max.iter()).any(|(lo, hi)| lo > hi) {
+            None
+        } else {
+            Some(Self { min, max })
+        }
+    }
+
+    /// The number of integer points in the box
+    pub fn volume(&self) -> T {
+        self.min
+            .iter()
+            .zip(self.max.iter())
+            .fold(T::from(1), |acc, (lo, hi)| acc * (*hi - *lo + T::from(1)))
+    }
+
+    pub fn contains_point(&self, point: &[T; N]) -> bool {
+        (0..N).all(|axis| self.min[axis] <= point[axis] && point[axis] <= self.max[axis])
+    }
+
+    pub fn contains(&self, other: &Self) -> bool {
+        (0..N).all(|axis| self.min[axis] <= other.min[axis] && other.max[axis] <= self.max[axis])
+    }
+
+    pub fn intersection(&self, other: &Self) -> Option<Self> {
+        let mut min = self.min;
+        let mut max = self.max;
+        for axis in 0..N {
+            min[axis] = T::max(self.min[axis], other.min[axis]);
+            max[axis] = T::min(self.max[axis], other.max[axis]);
+        }
+        Self::new(min, max)
+    }
+
+    /// Split into the part with `value < threshold` on `axis` and the part
+    /// with `value >= threshold`. Either side is None if it would be empty.
+    pub fn split(self, axis: usize, threshold: T) -> (Option<Self>, Option<Self>) {
+        if self.max[axis] < threshold {
+            (Some(self), None)
+        } else if self.min[axis] >= threshold {
+            (None, Some(self))
+        } else {
+            let mut below = self.clone();
+            let mut above = self;
+            below.max[axis] = threshold - T::from(1);
+            above.min[axis] = threshold;
+            (Some(below), Some(above))
+        }
+    }
+
+    /// Every integer point in the box, varying the last axis fastest
+    pub fn points(&self) -> Points<T, N> {
+        Points {
+            bounds: self.clone(),
+            next: Some(self.min),
+        }
+    }
+
+    /// The parts of `self` outside of `other`, as at most `2 * N` disjoint boxes
+    pub fn difference(&self, other: &Self) -> Vec<Self> {
+        if self.intersection(other).is_none() {
+            return vec![self.clone()];
+        }
+
+        // peel off the slabs below and above `other` one axis at a time;
+        // whatever is left over at the end is the intersection
+        let mut result: Vec<Self> = Vec::new();
+        let mut remainder = self.clone();
+        for axis in 0..N {
+            let (below, rest) = remainder.split(axis, other.min[axis]);
+            result.extend(below);
+            remainder = rest.unwrap();
+            // only step past `other.max` when something is above it, so a
+            // box reaching `T::MAX` doesn't overflow
+            if other.max[axis] < remainder.max[axis] {
+                let (rest, above) = remainder.split(axis, other.max[axis] + T::from(1));
+                result.extend(above);
+                remainder = rest.unwrap();
+            }
+        }
+        result
+    }
+}
+
+/// Iterator over the points of a `HyperBox`, see `HyperBox::points`
+pub struct Points<T, const N: usize> {
+    bounds: HyperBox<T, N>,
+    next: Option<[T; N]>,
+}
+
+impl<T, const N: usize> Iterator for Points<T, N>
+where
+    T: Ord + Copy + From<u8> + Add<Output = T>,
+{
+    type Item = [T; N];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let current = self.next?;
+
+        // count up like an odometer, rolling over into the axis before
+        let mut next = current;
+        self.next = None;
+        for axis in (0..N).rev() {
+            if next[axis] < self.bounds.max[axis] {
+                next[axis] = next[axis] + T::from(1);
+                self.next = Some(next);
+                break;
+            }
+            next[axis] = self.bounds.min[axis];
+        }
+
+        Some(current)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HyperBox;
+
+    fn cube(lo: i64, hi: i64) -> HyperBox<i64, 3> {
+        HyperBox::new([lo; 3], [hi; 3]).unwrap()
+    }
+
+    #[test]
+    fn test_volume() {
+        assert_eq!(cube(0, 0).volume(), 1);
+        assert_eq!(cube(-1, 1).volume(), 27);
+        assert_eq!(
+            HyperBox::new([1usize; 4], [4_000; 4]).unwrap().volume(),
+            4_000usize.pow(4)
+        );
+        assert!(HyperBox::new([1, 2], [1, 1]).is_none());
+    }
+
+    #[test]
+    fn test_intersection_and_containment() {
+        let big = cube(0, 9);
+        let small = cube(2, 4);
+        assert_eq!(big.intersection(&small), Some(small.clone()));
+        assert!(big.contains(&small));
+        assert!(!small.contains(&big));
+        assert!(big.contains_point(&[0, 9, 5]));
+        assert!(!big.contains_point(&[0, 10, 5]));
+        assert_eq!(cube(0, 1).intersection(&cube(2, 3)), None);
+        assert_eq!(cube(0, 2).intersection(&cube(2, 3)), Some(cube(2, 2)));
+    }
+
+    #[test]
+    fn test_points() {
+        let square = HyperBox::new([0, 5], [1, 6]).unwrap();
+        assert_eq!(
+            square.points().collect::<Vec<[i64; 2]>>(),
+            vec![[0, 5], [0, 6], [1, 5], [1, 6]]
+        );
+        assert_eq!(cube(-1, 1).points().count(), 27);
+        assert_eq!(cube(3, 3).points().collect::<Vec<[i64; 3]>>(), vec![[3; 3]]);
+    }
+
+    #[test]
+    fn test_split() {
+        let (below, above) = cube(0, 9).split(1, 5);
+        assert_eq!(below, HyperBox::new([0, 0, 0], [9, 4, 9]));
+        assert_eq!(above, HyperBox::new([0, 5, 0], [9, 9, 9]));
+
+        assert_eq!(cube(0, 9).split(0, 0), (None, Some(cube(0, 9))));
+        assert_eq!(cube(0, 9).split(0, 10), (Some(cube(0, 9)), None));
+    }
+
+    #[test]
+    fn test_difference() {
+        let outer = cube(0, 9);
+        let inner = cube(3, 5);
+        let pieces = outer.difference(&inner);
+
+        assert_eq!(pieces.len(), 6);
+        assert_eq!(
+            pieces.iter().map(|p| p.volume()).sum::<i64>(),
+            outer.volume() - inner.volume()
+        );
+        for (idx, piece) in pieces.iter().enumerate() {
+            assert!(outer.contains(piece));
+            assert!(piece.intersection(&inner).is_none());
+            for other in pieces[idx + 1..].iter() {
+                assert!(piece.intersection(other).is_none());
+            }
+        }
+
+        assert_eq!(cube(0, 1).difference(&cube(5, 6)), vec![cube(0, 1)]);
+        assert!(cube(2, 3).difference(&cube(0, 9)).is_empty());
+    }
+
+    #[test]
+    fn test_difference_at_type_max() {
+        let everything = HyperBox::new([0u8; 2], [u8::MAX; 2]).unwrap();
+        let top_corner = HyperBox::new([10u8; 2], [u8::MAX; 2]).unwrap();
+        let difference = everything.difference(&top_corner);
+        assert_eq!(
+            difference,
+            vec![
+                HyperBox::new([0, 0], [9, u8::MAX]).unwrap(),
+                HyperBox::new([10, 0], [u8::MAX, 9]).unwrap(),
+            ]
+        );
+        assert!(everything.difference(&everything).is_empty());
+
+        let wide = HyperBox::new([0usize], [usize::MAX]).unwrap();
+        let tail = HyperBox::new([5usize], [usize::MAX]).unwrap();
+        assert_eq!(
+            wide.difference(&tail),
+            vec![HyperBox::new([0], [4]).unwrap()]
+        );
+    }
+}
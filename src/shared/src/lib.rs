@@ -2,5 +2,7 @@ pub mod conversion;
 pub mod coords;
 pub mod coords3d;
 pub mod direction;
+pub mod dot;
+pub mod hyperbox;
 pub mod input;
 pub mod range;